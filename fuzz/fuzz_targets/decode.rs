@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: MIT
+
+#![no_main]
+
+use hid_json::{decode, text, DecodeOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let options = DecodeOptions::default()
+        .debug(true)
+        .collapse_redundant(true)
+        .with_state_diff(true)
+        .with_both_signs(true)
+        .warn_unaligned_fields(true)
+        .summary(true)
+        .with_reports(true);
+    let report_id = data.first().map(|&id| u32::from(id));
+    for options in [options.clone(), options.report_id(report_id)] {
+        if let Ok(decode) = decode(data, options) {
+            let _ = decode.report_map();
+            let _ = decode.tree();
+            let _ = text::to_text(&decode.items);
+            let _ = serde_json::to_vec(&decode);
+        }
+    }
+});
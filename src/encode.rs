@@ -0,0 +1,141 @@
+// SPDX-License-Identifier: MIT
+
+//! Conversion of the JSON format back into the bytes of a HID Report
+//! Descriptor, see `--encode`.
+//!
+//! Only the `name` and `value` of each item are used, each item is
+//! encoded with the smallest data size that fits its value.
+
+use crate::JsonItemName;
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+/// The subset of a [crate::JsonItem] required to encode the item
+#[derive(Deserialize)]
+pub struct EncodeItem {
+    /// Only used for error messages
+    pub offset: Option<usize>,
+    #[serde(rename = "name")]
+    pub item_name: JsonItemName,
+    pub value: Option<i64>,
+    /// The nested items of `--format json-tree`
+    #[serde(default)]
+    pub children: Vec<EncodeItem>,
+}
+
+/// Either the top-level object or, with `--items-only`, only the items
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EncodeInput {
+    Decode { items: Vec<EncodeItem> },
+    Items(Vec<EncodeItem>),
+}
+
+/// Encode the report descriptor described by the given JSON document
+pub fn from_json(json: &[u8]) -> Result<Vec<u8>> {
+    let items = match serde_json::from_slice(json).context("Invalid JSON document")? {
+        EncodeInput::Decode { items } => items,
+        EncodeInput::Items(items) => items,
+    };
+    encode(&items)
+}
+
+/// Encode the given items into the bytes of a report descriptor
+pub fn encode(items: &[EncodeItem]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    encode_items(items, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn encode_items(items: &[EncodeItem], bytes: &mut Vec<u8>) -> Result<()> {
+    for item in items {
+        let name = &item.item_name;
+        encode_item(item, bytes).with_context(|| match item.offset {
+            Some(offset) => format!("Failed to encode {name:?} item at offset {offset}"),
+            None => format!("Failed to encode {name:?} item"),
+        })?;
+        encode_items(&item.children, bytes)?;
+    }
+    Ok(())
+}
+
+/// The item type and tag bits of the prefix byte, see HID Specification
+/// Section 6.2.2.2, and whether the value is signed
+fn prefix(item_name: &JsonItemName) -> Result<(u8, bool)> {
+    const MAIN: u8 = 0b0000;
+    const GLOBAL: u8 = 0b0100;
+    const LOCAL: u8 = 0b1000;
+    let (tag, item_type, signed) = match item_name {
+        JsonItemName::Input => (0x8, MAIN, false),
+        JsonItemName::Output => (0x9, MAIN, false),
+        JsonItemName::Feature => (0xb, MAIN, false),
+        JsonItemName::Collection => (0xa, MAIN, false),
+        JsonItemName::EndCollection => (0xc, MAIN, false),
+        JsonItemName::UsagePage => (0x0, GLOBAL, false),
+        JsonItemName::LogicalMinimum => (0x1, GLOBAL, true),
+        JsonItemName::LogicalMaximum => (0x2, GLOBAL, true),
+        JsonItemName::PhysicalMinimum => (0x3, GLOBAL, true),
+        JsonItemName::PhysicalMaximum => (0x4, GLOBAL, true),
+        JsonItemName::UnitExponent => (0x5, GLOBAL, true),
+        JsonItemName::Unit => (0x6, GLOBAL, false),
+        JsonItemName::ReportSize => (0x7, GLOBAL, false),
+        JsonItemName::ReportId => (0x8, GLOBAL, false),
+        JsonItemName::ReportCount => (0x9, GLOBAL, false),
+        JsonItemName::Push => (0xa, GLOBAL, false),
+        JsonItemName::Pop => (0xb, GLOBAL, false),
+        JsonItemName::Usage => (0x0, LOCAL, false),
+        JsonItemName::UsageMinimum => (0x1, LOCAL, false),
+        JsonItemName::UsageMaximum => (0x2, LOCAL, false),
+        JsonItemName::DesignatorIndex => (0x3, LOCAL, false),
+        JsonItemName::DesignatorMinimum => (0x4, LOCAL, false),
+        JsonItemName::DesignatorMaximum => (0x5, LOCAL, false),
+        JsonItemName::StringIndex => (0x7, LOCAL, false),
+        JsonItemName::StringMinimum => (0x8, LOCAL, false),
+        JsonItemName::StringMaximum => (0x9, LOCAL, false),
+        JsonItemName::Delimiter => (0xa, LOCAL, false),
+        JsonItemName::LongItem => bail!("Long items are not supported"),
+        JsonItemName::Unknown | JsonItemName::Reserved => {
+            bail!("Item has no known tag")
+        }
+    };
+    Ok(((tag << 4) | item_type, signed))
+}
+
+fn encode_item(item: &EncodeItem, bytes: &mut Vec<u8>) -> Result<()> {
+    let (prefix, signed) = prefix(&item.item_name)?;
+    let Some(value) = item.value else {
+        bytes.push(prefix);
+        return Ok(());
+    };
+    // Negative values of unsigned items are 4-byte values above i32::MAX,
+    // see JSON_FORMAT.md
+    let max = if signed {
+        i64::from(i32::MAX)
+    } else {
+        i64::from(u32::MAX)
+    };
+    if value < i64::from(i32::MIN) || value > max {
+        bail!("Value {value} does not fit into 4 bytes");
+    }
+    let size = if signed {
+        match value {
+            -0x80..=0x7f => 1,
+            -0x8000..=0x7fff => 2,
+            _ => 4,
+        }
+    } else {
+        match value as u32 {
+            0..=0xff => 1,
+            0x100..=0xffff => 2,
+            _ => 4,
+        }
+    };
+    let size_code = match size {
+        1 => 1,
+        2 => 2,
+        _ => 3,
+    };
+    bytes.push(prefix | size_code);
+    bytes.extend_from_slice(&(value as u32).to_le_bytes()[..size]);
+    Ok(())
+}
@@ -0,0 +1,2079 @@
+// SPDX-License-Identifier: MIT
+
+//! Conversion of a HID Report Descriptor into a structure that serializes
+//! to the JSON format described in `JSON_FORMAT.md`.
+//!
+//! Use [decode] to decode the report descriptor bytes, the resulting
+//! [JsonDecode] can be serialized with any serde serializer.
+//!
+//! The report descriptor is untrusted input: [decode] and the functions
+//! of the resulting [JsonDecode] never panic, any descriptor that cannot
+//! be decoded results in an error. The `fuzz` directory has a
+//! `cargo fuzz` target to check this.
+
+pub mod encode;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod text;
+
+use anyhow::{bail, Context, Result};
+use hidreport::hid::*;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The version of the JSON format, see `JSON_FORMAT.md`
+pub const JSON_FORMAT_VERSION: &str = "1.0";
+
+/// Options to control what [decode] includes in the [JsonDecode].
+///
+/// New options may be added in the future, use [DecodeOptions::default]
+/// and the builder-style setters to create the options:
+///
+/// ```
+/// let options = hid_json::DecodeOptions::default()
+///     .skip_data(true)
+///     .with_reports(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    /// Don't include the data bytes of the descriptor and items
+    pub skip_data: bool,
+    /// Include a [JsonItemDebug] for each item
+    pub debug: bool,
+    /// Omit Global items that re-declare the value already in effect
+    pub collapse_redundant: bool,
+    /// Include a [JsonStateChange] for each Global item
+    pub with_state_diff: bool,
+    /// Include the signed and unsigned value for each Global item
+    pub with_both_signs: bool,
+    /// Warn about multi-byte variable fields that aren't byte-aligned
+    pub warn_unaligned_fields: bool,
+    /// Include a [JsonSummary]
+    pub summary: bool,
+    /// Include a [JsonReport] for each report
+    pub with_reports: bool,
+    /// Keep the fields of each report for [JsonDecode::report_map],
+    /// implied by `with_reports`
+    pub with_report_map: bool,
+    /// Only include the items of the report with this Report ID
+    pub report_id: Option<u32>,
+}
+
+impl DecodeOptions {
+    pub fn skip_data(mut self, skip_data: bool) -> Self {
+        self.skip_data = skip_data;
+        self
+    }
+
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn collapse_redundant(mut self, collapse_redundant: bool) -> Self {
+        self.collapse_redundant = collapse_redundant;
+        self
+    }
+
+    pub fn with_state_diff(mut self, with_state_diff: bool) -> Self {
+        self.with_state_diff = with_state_diff;
+        self
+    }
+
+    pub fn with_both_signs(mut self, with_both_signs: bool) -> Self {
+        self.with_both_signs = with_both_signs;
+        self
+    }
+
+    pub fn warn_unaligned_fields(mut self, warn_unaligned_fields: bool) -> Self {
+        self.warn_unaligned_fields = warn_unaligned_fields;
+        self
+    }
+
+    pub fn summary(mut self, summary: bool) -> Self {
+        self.summary = summary;
+        self
+    }
+
+    pub fn with_reports(mut self, with_reports: bool) -> Self {
+        self.with_reports = with_reports;
+        self
+    }
+
+    pub fn with_report_map(mut self, with_report_map: bool) -> Self {
+        self.with_report_map = with_report_map;
+        self
+    }
+
+    pub fn report_id(mut self, report_id: Option<u32>) -> Self {
+        self.report_id = report_id;
+        self
+    }
+}
+
+// Warning: These enum value names are JSON API
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub enum JsonItemType {
+    Global,
+    Main,
+    Local,
+    Long,
+    #[default]
+    Unknown,
+}
+
+// Warning: These enum value names are JSON API
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum JsonItemName {
+    #[default]
+    Unknown,
+    Input,
+    Output,
+    Feature,
+    Collection,
+    EndCollection,
+    UsagePage,
+    LogicalMinimum,
+    LogicalMaximum,
+    PhysicalMinimum,
+    PhysicalMaximum,
+    UnitExponent,
+    Unit,
+    ReportSize,
+    ReportId,
+    ReportCount,
+    Push,
+    Pop,
+    Reserved,
+    Usage,
+    UsageMinimum,
+    UsageMaximum,
+    DesignatorIndex,
+    DesignatorMinimum,
+    DesignatorMaximum,
+    StringIndex,
+    StringMinimum,
+    StringMaximum,
+    Delimiter,
+    LongItem,
+}
+
+impl<T> From<&T> for JsonItemName
+where
+    T: Item,
+{
+    fn from(item: &T) -> JsonItemName {
+        match item.item_type() {
+            ItemType::Main(mi) => match mi {
+                MainItem::Input(_) => JsonItemName::Input,
+                MainItem::Output(_) => JsonItemName::Output,
+                MainItem::Feature(_) => JsonItemName::Feature,
+                MainItem::Collection(_) => JsonItemName::Collection,
+                MainItem::EndCollection => JsonItemName::EndCollection,
+            },
+            ItemType::Global(gi) => match gi {
+                GlobalItem::UsagePage { .. } => JsonItemName::UsagePage,
+                GlobalItem::LogicalMinimum { .. } => JsonItemName::LogicalMinimum,
+                GlobalItem::LogicalMaximum { .. } => JsonItemName::LogicalMaximum,
+                GlobalItem::PhysicalMinimum { .. } => JsonItemName::PhysicalMinimum,
+                GlobalItem::PhysicalMaximum { .. } => JsonItemName::PhysicalMaximum,
+                GlobalItem::UnitExponent { .. } => JsonItemName::UnitExponent,
+                GlobalItem::Unit { .. } => JsonItemName::Unit,
+                GlobalItem::ReportSize { .. } => JsonItemName::ReportSize,
+                GlobalItem::ReportId { .. } => JsonItemName::ReportId,
+                GlobalItem::ReportCount { .. } => JsonItemName::ReportCount,
+                GlobalItem::Push => JsonItemName::Push,
+                GlobalItem::Pop => JsonItemName::Pop,
+                GlobalItem::Reserved => JsonItemName::Reserved,
+            },
+            ItemType::Local(li) => match li {
+                LocalItem::Usage { .. } => JsonItemName::Usage,
+                LocalItem::UsageMinimum { .. } => JsonItemName::UsageMinimum,
+                LocalItem::UsageMaximum { .. } => JsonItemName::UsageMaximum,
+                LocalItem::DesignatorIndex { .. } => JsonItemName::DesignatorIndex,
+                LocalItem::DesignatorMinimum { .. } => JsonItemName::DesignatorMinimum,
+                LocalItem::DesignatorMaximum { .. } => JsonItemName::DesignatorMaximum,
+                LocalItem::StringIndex { .. } => JsonItemName::StringIndex,
+                LocalItem::StringMinimum { .. } => JsonItemName::StringMinimum,
+                LocalItem::StringMaximum { .. } => JsonItemName::StringMaximum,
+                LocalItem::Delimiter { .. } => JsonItemName::Delimiter,
+                LocalItem::Reserved { .. } => JsonItemName::Reserved,
+            },
+            _ => JsonItemName::Unknown,
+        }
+    }
+}
+
+// Warning: These enum value names are JSON API
+#[derive(Debug, Serialize, JsonSchema)]
+pub enum JsonCollection {
+    Physical,
+    Logical,
+    Application,
+    Report,
+    NamedArray,
+    UsageSwitch,
+    UsageModifier,
+    Reserved,
+    VendorDefined(u8),
+}
+
+impl JsonCollection {
+    // can't implement try_from generically, so let's do this instead
+    fn lookup(item: &impl Item) -> Option<JsonCollection> {
+        match item.item_type() {
+            ItemType::Main(MainItem::Collection(c)) => Some(match c {
+                CollectionItem::Physical => JsonCollection::Physical,
+                CollectionItem::Logical => JsonCollection::Logical,
+                CollectionItem::Application => JsonCollection::Application,
+                CollectionItem::Report => JsonCollection::Report,
+                CollectionItem::NamedArray => JsonCollection::NamedArray,
+                CollectionItem::UsageSwitch => JsonCollection::UsageSwitch,
+                CollectionItem::UsageModifier => JsonCollection::UsageModifier,
+                CollectionItem::Reserved { .. } => JsonCollection::Reserved,
+                CollectionItem::VendorDefined { value } => JsonCollection::VendorDefined(value),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonDescriptor {
+    pub length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Default, Serialize, JsonSchema)]
+pub struct JsonItem {
+    pub offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Vec<u8>>,
+    #[serde(rename = "type")]
+    pub item_type: JsonItemType,
+    #[serde(rename = "name")]
+    pub item_name: JsonItemName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<i32>,
+
+    // Very optional fields
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<JsonCollection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_page: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_minimum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_maximum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_change: Option<JsonStateChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_signed: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_unsigned: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<JsonMainItemFlags>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<JsonUnit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution_unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub long_item: Option<JsonLongItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<JsonItemDebug>,
+}
+
+/// The data bits of an Input, Output or Feature item, see
+/// HID Specification Section 6.2.2.5
+#[derive(Serialize, JsonSchema)]
+pub struct JsonMainItemFlags {
+    pub constant: bool,
+    pub variable: bool,
+    pub relative: bool,
+    pub wrap: bool,
+    pub nonlinear: bool,
+    pub no_preferred_state: bool,
+    pub null_state: bool,
+    /// Reserved for Input items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volatile: Option<bool>,
+    pub buffered_bytes: bool,
+}
+
+impl JsonMainItemFlags {
+    fn lookup(item: &impl Item) -> Option<JsonMainItemFlags> {
+        let is_input = match item.item_type() {
+            ItemType::Main(MainItem::Input(_)) => true,
+            ItemType::Main(MainItem::Output(_)) | ItemType::Main(MainItem::Feature(_)) => false,
+            _ => return None,
+        };
+        let bits = item
+            .data()
+            .and_then(|data| u32::try_from(&data).ok())
+            .unwrap_or(0);
+        let bit = |n: u32| bits & (1 << n) != 0;
+        Some(JsonMainItemFlags {
+            constant: bit(0),
+            variable: bit(1),
+            relative: bit(2),
+            wrap: bit(3),
+            nonlinear: bit(4),
+            no_preferred_state: bit(5),
+            null_state: bit(6),
+            volatile: if is_input { None } else { Some(bit(7)) },
+            buffered_bytes: bit(8),
+        })
+    }
+}
+
+// Warning: These enum value names are JSON API
+#[derive(Debug, Serialize, JsonSchema)]
+pub enum JsonUnitSystem {
+    None,
+    SILinear,
+    SIRotation,
+    EnglishLinear,
+    EnglishRotation,
+    Reserved(u8),
+    VendorDefined,
+}
+
+/// The nibbles of a Unit item, see HID Specification Section 6.2.2.7.
+/// Exponents that are zero are omitted.
+#[derive(Serialize, JsonSchema)]
+pub struct JsonUnit {
+    pub system: JsonUnitSystem,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mass: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<i8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub luminous_intensity: Option<i8>,
+}
+
+impl JsonUnit {
+    fn lookup(item: &impl Item) -> Option<JsonUnit> {
+        let ItemType::Global(GlobalItem::Unit { .. }) = item.item_type() else {
+            return None;
+        };
+        let value = item.data().and_then(|data| u32::try_from(&data).ok())?;
+        Some(JsonUnit::from_value(value))
+    }
+
+    fn from_value(value: u32) -> JsonUnit {
+        let nibble = |n: u32| ((value >> (n * 4)) & 0xf) as u8;
+        // Exponents are 4-bit two's complement, 0x8..0xF are -8..-1
+        let exponent = |n: u32| match nibble(n) {
+            0 => None,
+            e if e >= 0x8 => Some(e as i8 - 16),
+            e => Some(e as i8),
+        };
+        let system = match nibble(0) {
+            0x0 => JsonUnitSystem::None,
+            0x1 => JsonUnitSystem::SILinear,
+            0x2 => JsonUnitSystem::SIRotation,
+            0x3 => JsonUnitSystem::EnglishLinear,
+            0x4 => JsonUnitSystem::EnglishRotation,
+            0xf => JsonUnitSystem::VendorDefined,
+            value => JsonUnitSystem::Reserved(value),
+        };
+        JsonUnit {
+            system,
+            length: exponent(1),
+            mass: exponent(2),
+            time: exponent(3),
+            temperature: exponent(4),
+            current: exponent(5),
+            luminous_intensity: exponent(6),
+        }
+    }
+
+    /// The unit as string, e.g. `"cm/s^2"`, or `None` for units without
+    /// a unit system or without any exponents
+    fn name(&self) -> Option<String> {
+        let names = match self.system {
+            JsonUnitSystem::SILinear => ["cm", "g", "s", "K", "A", "cd"],
+            JsonUnitSystem::SIRotation => ["rad", "g", "s", "K", "A", "cd"],
+            JsonUnitSystem::EnglishLinear => ["in", "slug", "s", "°F", "A", "cd"],
+            JsonUnitSystem::EnglishRotation => ["deg", "slug", "s", "°F", "A", "cd"],
+            _ => return None,
+        };
+        let exponents = [
+            self.length,
+            self.mass,
+            self.time,
+            self.temperature,
+            self.current,
+            self.luminous_intensity,
+        ];
+        let terms = |positive: bool| {
+            names
+                .iter()
+                .zip(exponents)
+                .filter_map(|(name, exponent)| {
+                    let exponent = exponent.filter(|&e| (e > 0) == positive)?.unsigned_abs();
+                    Some(if exponent == 1 {
+                        name.to_string()
+                    } else {
+                        format!("{name}^{exponent}")
+                    })
+                })
+                .collect::<Vec<String>>()
+        };
+        let (numerator, denominator) = (terms(true), terms(false));
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (true, true) => None,
+            (false, true) => Some(numerator.join("*")),
+            (true, false) => Some(format!("1/{}", denominator.join("*"))),
+            (false, false) => Some(format!("{}/{}", numerator.join("*"), denominator.join("*"))),
+        }
+    }
+}
+
+/// The tag and data of a long item, see HID Specification Section 6.2.2.3
+#[derive(Serialize, JsonSchema)]
+pub struct JsonLongItem {
+    pub tag: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonItemDebug {
+    pub prefix: u8,
+    pub size_code: u8,
+    pub data: Vec<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<i32>,
+}
+
+impl JsonItemDebug {
+    /// The debug details of an item with the given bytes and its value
+    /// as returned by [item_value]
+    fn new(bytes: &[u8], value: Option<i32>) -> JsonItemDebug {
+        JsonItemDebug {
+            prefix: bytes[0],
+            size_code: bytes[0] & 0x3,
+            data: bytes[1..].to_vec(),
+            value,
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonStateChange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<i32>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonDecode {
+    pub version: String,
+    pub descriptor: JsonDescriptor,
+    pub items: Vec<JsonItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapsed_items: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<JsonWarning>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<JsonSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reports: Option<Vec<JsonReport>>,
+
+    #[serde(skip)]
+    layout: ReportLayout,
+}
+
+impl JsonDecode {
+    /// A minimal description of each report, see `--format report-map`.
+    /// Empty unless decoded with [DecodeOptions::with_report_map] or
+    /// [DecodeOptions::with_reports].
+    pub fn report_map(&self) -> Vec<JsonReportMap> {
+        self.layout.report_map()
+    }
+
+    /// The items nested in their collections, see `--format json-tree`
+    pub fn tree(&self) -> Result<JsonTree<'_>> {
+        let mut items: Vec<JsonTreeItem> = Vec::new();
+        let mut stack: Vec<JsonTreeItem> = Vec::new();
+        for item in &self.items {
+            let node = JsonTreeItem {
+                item,
+                children: None,
+            };
+            let node = match item.item_name {
+                JsonItemName::Collection => {
+                    stack.push(JsonTreeItem {
+                        children: Some(Vec::new()),
+                        ..node
+                    });
+                    continue;
+                }
+                JsonItemName::EndCollection => {
+                    let Some(mut collection) = stack.pop() else {
+                        bail!(
+                            "EndCollection at offset {} without a matching Collection",
+                            item.offset
+                        );
+                    };
+                    collection.children.get_or_insert_with(Vec::new).push(node);
+                    collection
+                }
+                _ => node,
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.get_or_insert_with(Vec::new).push(node),
+                None => items.push(node),
+            }
+        }
+        if let Some(collection) = stack.first() {
+            bail!(
+                "Collection at offset {} is missing its EndCollection",
+                collection.item.offset
+            );
+        }
+
+        Ok(JsonTree {
+            version: &self.version,
+            descriptor: &self.descriptor,
+            items,
+            collapsed_items: self.collapsed_items,
+            warnings: &self.warnings,
+            summary: self.summary.as_ref(),
+            reports: self.reports.as_deref(),
+        })
+    }
+}
+
+/// The [JsonDecode] with the items nested in their collections
+#[derive(Serialize)]
+pub struct JsonTree<'a> {
+    pub version: &'a str,
+    pub descriptor: &'a JsonDescriptor,
+    pub items: Vec<JsonTreeItem<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapsed_items: Option<usize>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub warnings: &'a [JsonWarning],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<&'a JsonSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reports: Option<&'a [JsonReport]>,
+}
+
+#[derive(Serialize)]
+pub struct JsonTreeItem<'a> {
+    #[serde(flatten)]
+    pub item: &'a JsonItem,
+    /// The items inside a Collection, the last child is the EndCollection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<JsonTreeItem<'a>>>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonSummary {
+    /// The number of items referencing each Usage Page, keyed by the
+    /// Usage Page name
+    pub usage_page_histogram: BTreeMap<String, usize>,
+    /// The top-level Application collections, i.e. what this device is
+    pub applications: Vec<JsonApplication>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonApplication {
+    pub offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<String>,
+    pub report_ids: Vec<u32>,
+    pub input_fields: usize,
+    pub output_fields: usize,
+    pub feature_fields: usize,
+}
+
+/// Collects the top-level Application collections and the reports
+/// and fields within each.
+#[derive(Default)]
+struct ApplicationSummary {
+    /// The current collection nesting depth
+    depth: usize,
+    applications: Vec<JsonApplication>,
+}
+
+impl ApplicationSummary {
+    fn update(
+        &mut self,
+        item: &impl Item,
+        offset: usize,
+        globals: &FieldGlobals,
+        locals: &LocalState,
+    ) {
+        match item.item_type() {
+            ItemType::Main(MainItem::Collection(collection)) => {
+                if self.depth == 0 && matches!(collection, CollectionItem::Application) {
+                    self.applications.push(JsonApplication {
+                        offset,
+                        usage: locals.usages.first().map(|&u| usage_name(u)),
+                        report_ids: Vec::new(),
+                        input_fields: 0,
+                        output_fields: 0,
+                        feature_fields: 0,
+                    });
+                }
+                self.depth += 1;
+            }
+            ItemType::Main(MainItem::EndCollection) => self.depth = self.depth.saturating_sub(1),
+            _ => {
+                let Some(report_type) = JsonReportType::lookup(item) else {
+                    return;
+                };
+                let Some(application) = self.applications.last_mut().filter(|_| self.depth > 0)
+                else {
+                    return;
+                };
+                if is_constant_field(item) {
+                    return;
+                }
+                if let Some(report_id) = globals.report_id {
+                    if !application.report_ids.contains(&report_id) {
+                        application.report_ids.push(report_id);
+                    }
+                }
+                match report_type {
+                    JsonReportType::Input => application.input_fields += 1,
+                    JsonReportType::Output => application.output_fields += 1,
+                    JsonReportType::Feature => application.feature_fields += 1,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonWarning {
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Tracks the values of the Global items currently in effect,
+/// including the values saved by Push and restored by Pop.
+#[derive(Default)]
+struct GlobalState {
+    /// Indexed by the Global item tag
+    values: [Option<i32>; 16],
+    /// Whether the value was declared since the last Push or Pop
+    declared: [bool; 16],
+    stack: Vec<[Option<i32>; 16]>,
+}
+
+/// The effect a single Global item had on the [GlobalState]
+struct GlobalChange {
+    previous: Option<i32>,
+    current: Option<i32>,
+    /// `true` if the item re-declares the value that is already in
+    /// effect and was declared since the last Push or Pop.
+    redundant: bool,
+}
+
+impl GlobalState {
+    /// Update the state with the given item and its value. Returns the
+    /// change made by the item or `None` if the item isn't a Global item
+    /// with a value (Push and Pop have no value).
+    fn update(&mut self, item: &impl Item, value: Option<i32>) -> Option<GlobalChange> {
+        match item.item_type() {
+            ItemType::Global(GlobalItem::Push) => {
+                self.stack.push(self.values);
+                self.declared = Default::default();
+                None
+            }
+            ItemType::Global(GlobalItem::Pop) => {
+                if let Some(values) = self.stack.pop() {
+                    self.values = values;
+                }
+                self.declared = Default::default();
+                None
+            }
+            ItemType::Global(GlobalItem::Reserved) => None,
+            ItemType::Global(_) => {
+                let tag = (item.bytes()[0] >> 4) as usize;
+                let current = value;
+                let previous = self.values[tag];
+                let redundant = self.declared[tag] && current.is_some() && previous == current;
+                self.values[tag] = current;
+                self.declared[tag] = true;
+                Some(GlobalChange {
+                    previous,
+                    current,
+                    redundant,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The subset of Global items that define the shape of an
+/// Input, Output or Feature field.
+#[derive(Default)]
+struct FieldGlobals {
+    logical_minimum: Option<i32>,
+    logical_maximum: Option<i32>,
+    physical_minimum: Option<i32>,
+    physical_maximum: Option<i32>,
+    unit_exponent: Option<i32>,
+    unit: Option<u32>,
+    report_size: Option<u32>,
+    report_count: Option<u32>,
+    report_id: Option<u32>,
+    stack: Vec<FieldGlobals>,
+}
+
+impl FieldGlobals {
+    fn update(&mut self, item: &impl Item) {
+        let unsigned = || item.data().and_then(|data| u32::try_from(&data).ok());
+        match item.item_type() {
+            ItemType::Global(GlobalItem::LogicalMinimum { .. }) => {
+                self.logical_minimum = signed_value(item);
+            }
+            ItemType::Global(GlobalItem::LogicalMaximum { .. }) => {
+                self.logical_maximum = signed_value(item);
+            }
+            ItemType::Global(GlobalItem::PhysicalMinimum { .. }) => {
+                self.physical_minimum = signed_value(item);
+            }
+            ItemType::Global(GlobalItem::PhysicalMaximum { .. }) => {
+                self.physical_maximum = signed_value(item);
+            }
+            ItemType::Global(GlobalItem::UnitExponent { .. }) => {
+                // The HID Specification encodes the exponent as 4-bit
+                // two's complement, e.g. 0x0F is -1. Some devices use
+                // a sign-extended value instead, e.g. 0xFF for -1.
+                self.unit_exponent = signed_value(item).map(|e| match e {
+                    0x8..=0xf => e - 16,
+                    e => e,
+                });
+            }
+            ItemType::Global(GlobalItem::Unit { .. }) => self.unit = unsigned(),
+            ItemType::Global(GlobalItem::ReportSize { .. }) => self.report_size = unsigned(),
+            ItemType::Global(GlobalItem::ReportCount { .. }) => self.report_count = unsigned(),
+            ItemType::Global(GlobalItem::ReportId { .. }) => self.report_id = unsigned(),
+            ItemType::Global(GlobalItem::Push) => {
+                let pushed = FieldGlobals {
+                    stack: Vec::new(),
+                    ..*self
+                };
+                self.stack.push(pushed);
+            }
+            ItemType::Global(GlobalItem::Pop) => {
+                if let Some(popped) = self.stack.pop() {
+                    let stack = std::mem::take(&mut self.stack);
+                    *self = FieldGlobals { stack, ..popped };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The resolution in logical units per physical unit, see HID
+    /// Specification Section 6.2.2.7. Returns `None` for degenerate ranges.
+    fn resolution(&self) -> Option<f64> {
+        let (Some(logical_minimum), Some(logical_maximum)) =
+            (self.logical_minimum, self.logical_maximum)
+        else {
+            return None;
+        };
+        // Physical Minimum and Maximum default to the logical range
+        let (physical_minimum, physical_maximum) = match (
+            self.physical_minimum.unwrap_or(0),
+            self.physical_maximum.unwrap_or(0),
+        ) {
+            (0, 0) => (logical_minimum, logical_maximum),
+            range => range,
+        };
+        let logical = (i64::from(logical_maximum) - i64::from(logical_minimum)) as f64;
+        let physical = (i64::from(physical_maximum) - i64::from(physical_minimum)) as f64;
+        let exponent = self.unit_exponent.unwrap_or(0);
+        let resolution = logical / (physical * 10f64.powi(exponent));
+        resolution.is_finite().then_some(resolution)
+    }
+
+    /// Returns a warning message if the Report Size is too small for
+    /// the logical range of the field.
+    fn check_logical_range(&self) -> Option<String> {
+        let (Some(min), Some(max), Some(size)) =
+            (self.logical_minimum, self.logical_maximum, self.report_size)
+        else {
+            return None;
+        };
+        // an inverted range is a different bug
+        if min > max {
+            return None;
+        }
+        let needed = bits_needed(min, max);
+        if needed > size {
+            Some(format!(
+                "Logical range {min}..{max} needs {needed} bits but Report Size is {size}"
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// The maximum number of elements of a field that are listed
+/// individually, the same limit the Linux kernel applies to the number
+/// of usages of a field
+const MAX_FIELD_ELEMENTS: u32 = 12288;
+
+/// The Local items that apply to the next Main item
+#[derive(Default)]
+struct LocalState {
+    /// Usages in the order declared, as 32-bit extended usages
+    usages: Vec<u32>,
+    usage_minimum: Option<u32>,
+    usage_maximum: Option<u32>,
+}
+
+impl LocalState {
+    fn update(&mut self, item: &impl Item, usage_page: u16) {
+        let Some(value) = item.data().and_then(|data| u32::try_from(&data).ok()) else {
+            return;
+        };
+        // A 4-byte usage includes the usage page in the high word
+        let usage = if item.bytes().len() == 5 {
+            value
+        } else {
+            (u32::from(usage_page) << 16) | (value & 0xffff)
+        };
+        match item.item_type() {
+            ItemType::Local(LocalItem::Usage { .. }) => self.usages.push(usage),
+            ItemType::Local(LocalItem::UsageMinimum { .. }) => self.usage_minimum = Some(usage),
+            ItemType::Local(LocalItem::UsageMaximum { .. }) => self.usage_maximum = Some(usage),
+            _ => {}
+        }
+    }
+}
+
+// Warning: These enum value names are JSON API
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, JsonSchema)]
+pub enum JsonReportType {
+    Input,
+    Output,
+    Feature,
+}
+
+impl JsonReportType {
+    fn lookup(item: &impl Item) -> Option<JsonReportType> {
+        match item.item_type() {
+            ItemType::Main(MainItem::Input(_)) => Some(JsonReportType::Input),
+            ItemType::Main(MainItem::Output(_)) => Some(JsonReportType::Output),
+            ItemType::Main(MainItem::Feature(_)) => Some(JsonReportType::Feature),
+            _ => None,
+        }
+    }
+}
+
+/// A single Input, Output or Feature item within a report
+struct Field {
+    /// The offset of the Input, Output or Feature item
+    offset: usize,
+    /// Excluding the Report ID byte
+    bit_offset: u32,
+    /// Report Size × Report Count, saturated at `u32::MAX`
+    bits: u32,
+    report_size: u32,
+    report_count: u32,
+    is_constant: bool,
+    is_variable: bool,
+    is_signed: bool,
+    /// The declared Usages, as 32-bit extended usages
+    usages: Vec<u32>,
+    /// The Usage Minimum and Usage Maximum
+    usage_range: Option<(u32, u32)>,
+    /// The usage of the innermost enclosing collection
+    collection: Option<u32>,
+    /// The Unit Exponent, if nonzero
+    unit_exponent: Option<i32>,
+    /// The Unit, if nonzero
+    unit: Option<u32>,
+    /// Only for fields with a Unit or Unit Exponent
+    resolution: Option<f64>,
+}
+
+/// A single value within a [Field]
+struct FieldElement {
+    bits: u32,
+    signed: bool,
+    usage: Option<u32>,
+}
+
+impl Field {
+    /// The individual elements of this field, up to [MAX_FIELD_ELEMENTS].
+    /// A constant (padding) field is a single element spanning the whole
+    /// field. Beyond [MAX_FIELD_ELEMENTS], one last element spans the
+    /// remaining bits of the field so the elements add up to its `bits`.
+    fn elements(&self) -> Vec<FieldElement> {
+        if self.is_constant {
+            return vec![FieldElement {
+                bits: self.bits,
+                signed: false,
+                usage: None,
+            }];
+        }
+        let count = self.report_count.min(MAX_FIELD_ELEMENTS);
+        let mut elements: Vec<FieldElement> = (0..count)
+            .map(|idx| FieldElement {
+                bits: self.report_size,
+                signed: self.is_signed,
+                usage: self.is_variable.then(|| self.variable_usage(idx)).flatten(),
+            })
+            .collect();
+        if self.report_count > count {
+            elements.push(FieldElement {
+                bits: self
+                    .bits
+                    .saturating_sub(self.report_size.saturating_mul(count)),
+                signed: false,
+                usage: None,
+            });
+        }
+        elements
+    }
+
+    /// The bit offset of the element at `index` of [Field::elements]
+    /// within the report, saturated at `u32::MAX`
+    #[cfg(feature = "sqlite")]
+    fn element_offset(&self, index: u32) -> u32 {
+        self.bit_offset
+            .saturating_add(index.saturating_mul(self.report_size))
+    }
+
+    /// The usage of the element at `index` of a variable field. The
+    /// declared usages apply in order, followed by those of the usage
+    /// range. Where there are fewer usages than elements, the last usage
+    /// applies to the remaining elements.
+    fn variable_usage(&self, index: u32) -> Option<u32> {
+        if let Some(&usage) = self.usages.get(index as usize) {
+            return Some(usage);
+        }
+        let remaining = index - self.usages.len() as u32;
+        match self.usage_range {
+            Some((min, max)) if min <= max => Some(min + remaining.min(max - min)),
+            _ => self.usages.last().copied(),
+        }
+    }
+
+    /// The usages listed in [JsonReportField::usages]: one usage per
+    /// element for variable fields, every usage an array field can report
+    /// otherwise, the declared usages followed by those of the usage
+    /// range. Up to [MAX_FIELD_ELEMENTS] usages.
+    fn listed_usages(&self) -> Vec<u32> {
+        match (self.is_constant, self.is_variable) {
+            (true, _) => Vec::new(),
+            (false, true) => (0..self.report_count.min(MAX_FIELD_ELEMENTS))
+                .map_while(|idx| self.variable_usage(idx))
+                .collect(),
+            (false, false) => self
+                .usages
+                .iter()
+                .copied()
+                .chain(
+                    self.usage_range
+                        .into_iter()
+                        .flat_map(|(min, max)| min..=max),
+                )
+                .take(MAX_FIELD_ELEMENTS as usize)
+                .collect(),
+        }
+    }
+
+    /// The well-known purpose of this field in a report of the given
+    /// type, if any
+    fn role(&self, report_type: JsonReportType) -> Option<JsonFieldRole> {
+        if self.is_constant {
+            return None;
+        }
+        let usage = if self.is_variable {
+            self.variable_usage(0)
+        } else {
+            self.usage_range
+                .map(|(min, _)| min)
+                .or_else(|| self.usages.first().copied())
+        }?;
+        match (self.is_variable, usage >> 16, usage & 0xffff) {
+            (false, 0x07, _) => Some(JsonFieldRole::KeycodeArray),
+            (false, 0x0c, _) => Some(JsonFieldRole::ConsumerControlArray),
+            // The Data Field usages of the Sensors page, see HUT Section 22
+            (true, 0x20, 0x0450..=0x045f) => Some(JsonFieldRole::MotionData),
+            (true, 0x20, 0x0470..=0x048f) => Some(JsonFieldRole::OrientationData),
+            // The position Data Values of the Eye and Head Trackers page
+            (true, 0x12, 0x21..=0x2f) => Some(JsonFieldRole::GazeData),
+            // The 64-byte CTAPHID packets, see the FIDO CTAP Specification
+            (_, 0xf1d0, id)
+                if self.report_size == 8
+                    && self.report_count == 64
+                    && matches!(
+                        (report_type, id),
+                        (JsonReportType::Input, 0x20) | (JsonReportType::Output, 0x21)
+                    ) =>
+            {
+                Some(JsonFieldRole::FidoReportData)
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Report {
+    report_id: u32,
+    report_type: JsonReportType,
+    /// Length in bits, excluding the Report ID byte
+    bits: u32,
+    fields: Vec<Field>,
+}
+
+impl Report {
+    /// The length of the report in bytes, including the Report ID byte
+    fn length(&self) -> u32 {
+        self.bits.div_ceil(8) + u32::from(self.report_id != 0)
+    }
+}
+
+/// Collects the fields of each report while iterating through the
+/// Input, Output and Feature items.
+#[derive(Default)]
+struct ReportLayout {
+    /// Whether to keep the fields of each report, otherwise only the
+    /// report lengths are tracked
+    keep_fields: bool,
+    /// In the order the reports first appear in the report descriptor
+    reports: Vec<Report>,
+    /// The usage of each currently open collection, innermost last
+    collections: Vec<Option<u32>>,
+}
+
+impl ReportLayout {
+    /// Track the Collection and End Collection items
+    fn update_collections(&mut self, item: &impl Item, locals: &LocalState) {
+        match item.item_type() {
+            ItemType::Main(MainItem::Collection(_)) => {
+                self.collections.push(locals.usages.first().copied())
+            }
+            ItemType::Main(MainItem::EndCollection) => {
+                self.collections.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Add the field for the given Input, Output or Feature item and
+    /// return the bit offset of that field within its report, excluding
+    /// the Report ID byte. A warning is added if the report length
+    /// overflows, the length is saturated at `u32::MAX` bits.
+    fn add_field(
+        &mut self,
+        report_type: JsonReportType,
+        item: &impl Item,
+        offset: usize,
+        globals: &FieldGlobals,
+        locals: &LocalState,
+        warnings: &mut Vec<JsonWarning>,
+    ) -> u32 {
+        let report_id = globals.report_id.unwrap_or(0);
+        let index = match self
+            .reports
+            .iter()
+            .position(|r| r.report_id == report_id && r.report_type == report_type)
+        {
+            Some(index) => index,
+            None => {
+                self.reports.push(Report {
+                    report_id,
+                    report_type,
+                    bits: 0,
+                    fields: Vec::new(),
+                });
+                self.reports.len() - 1
+            }
+        };
+        let report = &mut self.reports[index];
+        let report_size = globals.report_size.unwrap_or(0);
+        let report_count = globals.report_count.unwrap_or(0);
+        let is_constant = is_constant_field(item);
+        let is_variable = item.bytes().get(1).is_some_and(|b| b & 0x2 != 0);
+        let bit_offset = report.bits;
+        let bits = report_size.checked_mul(report_count);
+        if bits.and_then(|bits| bit_offset.checked_add(bits)).is_none() {
+            warnings.push(JsonWarning {
+                offset,
+                message: format!(
+                    "Report Size {report_size} and Report Count {report_count} exceed the \
+                     maximum report length of {} bits",
+                    u32::MAX
+                ),
+            });
+        }
+        if report_count > MAX_FIELD_ELEMENTS && !is_constant {
+            warnings.push(JsonWarning {
+                offset,
+                message: format!(
+                    "Report Count {report_count} exceeds the maximum of {MAX_FIELD_ELEMENTS} \
+                     elements per field, only the first {MAX_FIELD_ELEMENTS} are listed"
+                ),
+            });
+        }
+        let bits = bits.unwrap_or(u32::MAX);
+        report.bits = report.bits.saturating_add(bits);
+        if !self.keep_fields {
+            return bit_offset;
+        }
+        let (usages, usage_range) = if is_constant {
+            (Vec::new(), None)
+        } else {
+            (
+                locals.usages.clone(),
+                locals.usage_minimum.zip(locals.usage_maximum),
+            )
+        };
+        report.fields.push(Field {
+            offset,
+            bit_offset,
+            bits,
+            report_size,
+            report_count,
+            is_constant,
+            is_variable,
+            is_signed: globals.logical_minimum.is_some_and(|min| min < 0),
+            usages,
+            usage_range,
+            collection: self.collections.last().copied().flatten(),
+            unit_exponent: globals.unit_exponent.filter(|&e| e != 0),
+            unit: globals.unit.filter(|&u| u != 0),
+            resolution: (globals.unit_exponent.unwrap_or(0) != 0 || globals.unit.unwrap_or(0) != 0)
+                .then(|| globals.resolution())
+                .flatten(),
+        });
+        bit_offset
+    }
+
+    /// A minimal description of each report, see `--format report-map`
+    fn report_map(&self) -> Vec<JsonReportMap> {
+        if !self.keep_fields {
+            return Vec::new();
+        }
+        self.reports
+            .iter()
+            .map(|report| JsonReportMap {
+                report_id: report.report_id,
+                report_type: report.report_type,
+                length: report.length(),
+                fields: report
+                    .fields
+                    .iter()
+                    .flat_map(|field| {
+                        field
+                            .elements()
+                            .into_iter()
+                            .map(|element| JsonReportMapField {
+                                usage: element.usage.map(usage_name),
+                                bits: element.bits,
+                                signed: element.signed,
+                            })
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// The fields of each report with their bit offsets, see `--with-reports`
+    fn reports(&self) -> Vec<JsonReport> {
+        self.reports
+            .iter()
+            .map(|report| JsonReport {
+                report_id: report.report_id,
+                report_type: report.report_type,
+                length: report.length(),
+                fields: report
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let array_range = field.usage_range.filter(|_| !field.is_variable);
+                        JsonReportField {
+                            offset: field.offset,
+                            bit_offset: field.bit_offset,
+                            bits: field.bits,
+                            report_size: field.report_size,
+                            report_count: field.report_count,
+                            constant: field.is_constant,
+                            variable: field.is_variable,
+                            usages: field.listed_usages().into_iter().map(usage_name).collect(),
+                            usage_minimum: array_range.map(|(min, _)| usage_name(min)),
+                            usage_maximum: array_range.map(|(_, max)| usage_name(max)),
+                            role: field.role(report.report_type),
+                            collection: field.collection.map(usage_name),
+                            unit_exponent: field.unit_exponent,
+                            resolution: field.resolution,
+                            resolution_unit: field
+                                .unit
+                                .and_then(|unit| JsonUnit::from_value(unit).name()),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonReport {
+    pub report_id: u32,
+    #[serde(rename = "type")]
+    pub report_type: JsonReportType,
+    /// In bytes, including the Report ID byte
+    pub length: u32,
+    pub fields: Vec<JsonReportField>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct JsonReportField {
+    /// The offset of the Input, Output or Feature item
+    pub offset: usize,
+    /// Excluding the Report ID byte
+    pub bit_offset: u32,
+    pub bits: u32,
+    pub report_size: u32,
+    pub report_count: u32,
+    pub constant: bool,
+    /// Whether the field has one value per usage (Variable) or reports
+    /// the usages that are active (Array)
+    pub variable: bool,
+    /// One usage per element for variable fields, every usage an array
+    /// field can report otherwise
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub usages: Vec<String>,
+    /// The Usage Minimum of an array field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_minimum: Option<String>,
+    /// The Usage Maximum of an array field
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_maximum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<JsonFieldRole>,
+    /// The usage of the innermost collection the field is in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+    /// The Unit Exponent, if nonzero
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_exponent: Option<i32>,
+    /// Logical units per physical unit, only for fields with a Unit or
+    /// Unit Exponent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution_unit: Option<String>,
+}
+
+/// The well-known purpose of a [JsonReportField]
+// Warning: These enum value names are JSON API
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, JsonSchema)]
+pub enum JsonFieldRole {
+    /// An array of Keyboard/Keypad page usages, the keys currently pressed
+    KeycodeArray,
+    /// An array of Consumer page usages, e.g. media keys
+    ConsumerControlArray,
+    /// A motion Data Field of a sensor, e.g. acceleration or angular
+    /// velocity
+    MotionData,
+    /// An orientation Data Field of a sensor, e.g. a quaternion or tilt
+    OrientationData,
+    /// A position of an eye or head tracker, e.g. the gaze point
+    GazeData,
+    /// The 64 bytes of Input or Output Report Data of a FIDO
+    /// authenticator
+    FidoReportData,
+}
+
+#[derive(Serialize)]
+pub struct JsonReportMap {
+    pub report_id: u32,
+    #[serde(rename = "type")]
+    pub report_type: JsonReportType,
+    pub length: u32,
+    pub fields: Vec<JsonReportMapField>,
+}
+
+#[derive(Serialize)]
+pub struct JsonReportMapField {
+    pub usage: Option<String>,
+    pub bits: u32,
+    pub signed: bool,
+}
+
+/// The report map together with the report descriptor, see
+/// `--include-raw-descriptor-in-reports`
+#[derive(Serialize)]
+pub struct JsonReportMapWithDescriptor<'a> {
+    pub descriptor: &'a JsonDescriptor,
+    pub reports: &'a [JsonReportMap],
+}
+
+/// The name of a 32-bit extended usage or its hex value if the usage
+/// isn't known.
+fn usage_name(usage: u32) -> String {
+    let (usage_page, usage_id) = ((usage >> 16) as u16, (usage & 0xffff) as u16);
+    hut::Usage::new_from_page_and_id(usage_page, usage_id)
+        .map(|u| format!("{u}"))
+        .unwrap_or_else(|_| format!("{usage:#010x}"))
+}
+
+/// Returns a warning message if a multi-byte variable field starts at a
+/// bit offset that isn't a byte boundary.
+fn check_alignment(item: &impl Item, globals: &FieldGlobals, bit_offset: u32) -> Option<String> {
+    let is_variable = item.bytes().get(1).is_some_and(|b| b & 0x2 != 0);
+    let size = globals.report_size?;
+    if is_constant_field(item)
+        || !is_variable
+        || size == 0
+        || !size.is_multiple_of(8)
+        || bit_offset.is_multiple_of(8)
+    {
+        return None;
+    }
+    let report = match globals.report_id {
+        Some(id) => format!("report {id}"),
+        None => "the report".to_string(),
+    };
+    Some(format!(
+        "{size}-bit field at bit offset {bit_offset} of {report} is not byte-aligned"
+    ))
+}
+
+/// Checks for structural errors in the report descriptor. Each check
+/// adds its warnings while iterating through the items, checks that need
+/// the whole report descriptor add their warnings in [Validation::finish].
+#[derive(Default)]
+struct Validation {
+    /// The offsets of the Push items not yet popped
+    pushes: Vec<usize>,
+    /// The offsets of the Collection items not yet closed
+    collections: Vec<usize>,
+}
+
+impl Validation {
+    fn update(
+        &mut self,
+        item: &impl Item,
+        offset: usize,
+        globals: &FieldGlobals,
+        warnings: &mut Vec<JsonWarning>,
+    ) {
+        let mut warn = |message: String| warnings.push(JsonWarning { offset, message });
+        // Note: the guards of Pop and EndCollection pop the stack
+        match item.item_type() {
+            ItemType::Global(GlobalItem::Push) => self.pushes.push(offset),
+            ItemType::Global(GlobalItem::Pop) if self.pushes.pop().is_none() => {
+                warn("Pop without a matching Push".to_string());
+            }
+            ItemType::Global(GlobalItem::ReportCount { .. }) if globals.report_count == Some(0) => {
+                warn("Report Count is 0".to_string());
+            }
+            ItemType::Main(MainItem::Collection(_)) => self.collections.push(offset),
+            ItemType::Main(MainItem::EndCollection) if self.collections.pop().is_none() => {
+                warn("EndCollection without a matching Collection".to_string());
+            }
+            ItemType::Main(MainItem::Input(_))
+            | ItemType::Main(MainItem::Output(_))
+            | ItemType::Main(MainItem::Feature(_)) => {
+                let name = format!("{:?}", JsonItemName::from(item));
+                if globals.report_size.is_none() {
+                    warn(format!("{name} without a preceding Report Size"));
+                }
+                if globals.report_count.is_none() {
+                    warn(format!("{name} without a preceding Report Count"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(self, warnings: &mut Vec<JsonWarning>) {
+        for offset in self.pushes {
+            warnings.push(JsonWarning {
+                offset,
+                message: "Push without a matching Pop".to_string(),
+            });
+        }
+        for offset in self.collections {
+            warnings.push(JsonWarning {
+                offset,
+                message: "Collection without a matching EndCollection".to_string(),
+            });
+        }
+    }
+}
+
+/// The number of bits required to represent all values in `min..=max`,
+/// using two's complement if `min` is negative.
+fn bits_needed(min: i32, max: i32) -> u32 {
+    let (min, max) = (i64::from(min), i64::from(max));
+    (1..=32)
+        .find(|bits| {
+            if min < 0 {
+                min >= -(1i64 << (bits - 1)) && max < (1i64 << (bits - 1))
+            } else {
+                max < (1i64 << bits)
+            }
+        })
+        .unwrap_or(32)
+}
+
+/// The item data interpreted as a signed value, sign-extended
+/// according to the number of data bytes.
+fn signed_value(item: &impl Item) -> Option<i32> {
+    match item.bytes()[1..] {
+        [b0] => Some(i32::from(b0 as i8)),
+        [b0, b1] => Some(i32::from(i16::from_le_bytes([b0, b1]))),
+        [b0, b1, b2, b3] => Some(i32::from_le_bytes([b0, b1, b2, b3])),
+        _ => None,
+    }
+}
+
+fn is_constant_field(item: &impl Item) -> bool {
+    item.bytes().get(1).is_some_and(|b| b & 0x1 != 0)
+}
+
+/// Check if `bytes` failed to parse because it is a valid report
+/// descriptor preceded by a report ID byte and if so, return that
+/// report ID. This happens when the data was captured from a hidraw
+/// report rather than the report descriptor.
+pub fn leading_report_id(bytes: &[u8]) -> Option<u8> {
+    let (&first, rest) = bytes.split_first()?;
+    let rdesc_items = ReportDescriptorItems::try_from(rest).ok()?;
+    let declared = rdesc_items
+        .iter()
+        .map(|rdesc_item| rdesc_item.item())
+        .any(|item| {
+            matches!(
+                item.item_type(),
+                ItemType::Global(GlobalItem::ReportId { .. })
+            ) && item.data().and_then(|data| u32::try_from(&data).ok()) == Some(u32::from(first))
+        });
+    declared.then_some(first)
+}
+
+/// The item data as the i32 provided in the `value` field. Items
+/// the HID Specification defines as signed are sign-extended, all
+/// others are unsigned.
+fn item_value(item: &impl Item, offset: usize) -> Result<Option<i32>> {
+    let Some(data) = item.data() else {
+        return Ok(None);
+    };
+    let value = u32::try_from(&data).ok().with_context(|| {
+        format!(
+            "Invalid data {:02x?} for {:?} item at offset {offset}",
+            item.bytes(),
+            JsonItemName::from(item)
+        )
+    })?;
+    Ok(match item.item_type() {
+        ItemType::Global(GlobalItem::LogicalMinimum { .. })
+        | ItemType::Global(GlobalItem::LogicalMaximum { .. })
+        | ItemType::Global(GlobalItem::PhysicalMinimum { .. })
+        | ItemType::Global(GlobalItem::PhysicalMaximum { .. })
+        | ItemType::Global(GlobalItem::UnitExponent { .. }) => signed_value(item),
+        _ => Some(value as i32),
+    })
+}
+
+fn lookup_usage_page(item: &impl Item) -> Option<hut::UsagePage> {
+    match item.item_type() {
+        ItemType::Global(GlobalItem::UsagePage { .. }) => {
+            let up = item.data().and_then(|data| u32::try_from(&data).ok())? as u16;
+            hut::UsagePage::try_from(up).ok()
+        }
+        _ => None,
+    }
+}
+
+/// The Usage Page and Usage ID of a Usage, UsageMinimum or UsageMaximum
+/// item. A 4-byte usage is an extended usage with the Usage Page in the
+/// high word, overriding the current `usage_page`.
+fn extended_usage(item: &impl Item, usage_page: u16) -> Option<(u16, u16)> {
+    match item.item_type() {
+        ItemType::Local(LocalItem::Usage { .. })
+        | ItemType::Local(LocalItem::UsageMinimum { .. })
+        | ItemType::Local(LocalItem::UsageMaximum { .. }) => {
+            let value = item.data().and_then(|data| u32::try_from(&data).ok())?;
+            if item.bytes().len() == 5 {
+                Some(((value >> 16) as u16, value as u16))
+            } else {
+                Some((usage_page, value as u16))
+            }
+        }
+        _ => None,
+    }
+}
+
+fn lookup_usage(item: &impl Item, usage_page: u16) -> Option<hut::Usage> {
+    match item.item_type() {
+        ItemType::Local(LocalItem::Usage { .. }) => {
+            let (page, id) = extended_usage(item, usage_page)?;
+            hut::Usage::new_from_page_and_id(page, id).ok()
+        }
+        _ => None,
+    }
+}
+
+/// The usage of a UsageMinimum or UsageMaximum item, depending on `minimum`
+fn lookup_usage_limit(item: &impl Item, usage_page: u16, minimum: bool) -> Option<hut::Usage> {
+    match (item.item_type(), minimum) {
+        (ItemType::Local(LocalItem::UsageMinimum { .. }), true)
+        | (ItemType::Local(LocalItem::UsageMaximum { .. }), false) => {
+            let (page, id) = extended_usage(item, usage_page)?;
+            hut::Usage::new_from_page_and_id(page, id).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Only the items of the report with the given Report ID, see `--report-id`.
+///
+/// This includes all items before the first ReportId item, the items
+/// where the given Report ID is in effect and the context of those items:
+/// the collections they are in (including the Usage items preceding each
+/// collection) and the UsagePage in effect at each kept Collection or
+/// ReportId item.
+fn filter_report_id(
+    items: Vec<JsonItem>,
+    report_ids: &[Option<u32>],
+    report_id: u32,
+) -> Result<Vec<JsonItem>> {
+    if !report_ids.contains(&Some(report_id)) {
+        bail!("Report ID {report_id} is not declared in the report descriptor");
+    }
+
+    let mut keep: Vec<bool> = report_ids
+        .iter()
+        .map(|id| id.is_none_or(|id| id == report_id))
+        .collect();
+    // Collections are only kept if they contain an item of this report
+    let mut needed = vec![false; items.len()];
+    let mut collections: Vec<usize> = Vec::new();
+    for (idx, item) in items.iter().enumerate() {
+        match item.item_name {
+            JsonItemName::Collection => {
+                keep[idx] = false;
+                collections.push(idx);
+            }
+            JsonItemName::EndCollection => {
+                keep[idx] = false;
+                if let Some(start) = collections.pop() {
+                    keep[start] = needed[start];
+                    keep[idx] = needed[start];
+                }
+            }
+            _ => {
+                if report_ids[idx] == Some(report_id) {
+                    for &start in &collections {
+                        needed[start] = true;
+                    }
+                }
+            }
+        }
+    }
+    // Unbalanced collections
+    for start in collections {
+        keep[start] = needed[start];
+    }
+
+    let mut last_usage_page: Option<usize> = None;
+    for (idx, item) in items.iter().enumerate() {
+        match item.item_name {
+            JsonItemName::UsagePage => last_usage_page = Some(idx),
+            JsonItemName::Collection | JsonItemName::ReportId if keep[idx] => {
+                if let Some(usage_page) = last_usage_page {
+                    keep[usage_page] = true;
+                }
+                if let JsonItemName::Collection = item.item_name {
+                    for local in (0..idx)
+                        .rev()
+                        .take_while(|&local| matches!(items[local].item_type, JsonItemType::Local))
+                    {
+                        keep[local] = true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(items
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(item, keep)| keep.then_some(item))
+        .collect())
+}
+
+/// The length in bytes of the item at the given offset or `None` if
+/// the item is truncated
+fn item_length(bytes: &[u8], offset: usize) -> Option<usize> {
+    let prefix = *bytes.get(offset)?;
+    // Long items have the data size in the byte following the prefix,
+    // see HID Specification Section 6.2.2.3
+    let length = if prefix == 0xfe {
+        3 + usize::from(*bytes.get(offset + 1)?)
+    } else {
+        1 + [0, 1, 2, 4][usize::from(prefix & 0x3)]
+    };
+    (offset + length <= bytes.len()).then_some(length)
+}
+
+/// A long item removed from the report descriptor before parsing
+struct LongItem {
+    offset: usize,
+    bytes: Vec<u8>,
+}
+
+/// Split the report descriptor into the short items and the long items.
+/// The short items remain in order, without any long items in between.
+fn split_long_items(bytes: &[u8]) -> (Vec<u8>, Vec<LongItem>) {
+    let mut short_items = Vec::new();
+    let mut long_items = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let Some(length) = item_length(bytes, offset) else {
+            // Leave the truncated item to the parser
+            short_items.extend_from_slice(&bytes[offset..]);
+            break;
+        };
+        let item = &bytes[offset..offset + length];
+        if item[0] == 0xfe {
+            long_items.push(LongItem {
+                offset,
+                bytes: item.to_vec(),
+            });
+        } else {
+            short_items.extend_from_slice(item);
+        }
+        offset += length;
+    }
+    (short_items, long_items)
+}
+
+/// The offset in the original report descriptor of the short item at
+/// `offset` in the short items returned by [split_long_items]
+fn original_offset(long_items: &[LongItem], offset: usize) -> usize {
+    let mut offset = offset;
+    for long_item in long_items {
+        if long_item.offset > offset {
+            break;
+        }
+        offset += long_item.bytes.len();
+    }
+    offset
+}
+
+/// The offset of the first item in `bytes` that fails to parse, given
+/// a function that returns whether the items in a slice parse. Once an
+/// item fails to parse, so does every slice including that item, so the
+/// failing item is found with a binary search over the item boundaries.
+fn failing_item_offset(bytes: &[u8], parses: impl Fn(&[u8]) -> bool) -> Option<usize> {
+    let mut items: Vec<(usize, usize)> = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        // A truncated item extends to the end
+        let end = item_length(bytes, offset).map_or(bytes.len(), |length| offset + length);
+        items.push((offset, end));
+        offset = end;
+    }
+    let index = items.partition_point(|&(_, end)| parses(&bytes[..end]));
+    items.get(index).map(|&(offset, _)| offset)
+}
+
+/// Describe where parsing the report descriptor failed, including
+/// the bytes around the offending item, e.g. `05 01 [26 ff]`.
+/// `short_items` and `long_items` are as returned by [split_long_items].
+fn parse_error_context(bytes: &[u8], short_items: &[u8], long_items: &[LongItem]) -> String {
+    let Some(offset) = failing_item_offset(short_items, |items| {
+        ReportDescriptorItems::try_from(items).is_ok()
+    }) else {
+        return "Failed to parse the report descriptor".to_string();
+    };
+    let offset = original_offset(long_items, offset);
+    let length = item_length(bytes, offset);
+    let end = length.map_or(bytes.len(), |length| offset + length);
+    let hex = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let before = &bytes[offset.saturating_sub(8)..offset];
+    let snippet = if before.is_empty() {
+        format!("[{}]", hex(&bytes[offset..end]))
+    } else {
+        format!("{} [{}]", hex(before), hex(&bytes[offset..end]))
+    };
+    match length {
+        None => format!(
+            "Failed to parse the report descriptor: the item at offset {offset} exceeds the \
+             descriptor length of {} bytes: {snippet}",
+            bytes.len()
+        ),
+        Some(_) => format!(
+            "Failed to parse the report descriptor: invalid item at offset {offset}: {snippet}"
+        ),
+    }
+}
+
+/// Decode the given report descriptor bytes
+pub fn decode(bytes: &[u8], options: DecodeOptions) -> Result<JsonDecode> {
+    let mut items = Vec::new();
+    let mut report_ids = Vec::new();
+    let mut decode = decode_items(bytes, &options, |report_id, item| {
+        report_ids.push(report_id);
+        items.push(item);
+        Ok(())
+    })?;
+    decode.items = match options.report_id {
+        Some(report_id) => filter_report_id(items, &report_ids, report_id)?,
+        None => items,
+    };
+    Ok(decode)
+}
+
+/// Decode the given report descriptor bytes and pass each item to `sink`
+/// as soon as it is decoded, see `--ndjson`. The returned [JsonDecode]
+/// has no items.
+///
+/// Filtering by Report ID is not supported.
+pub fn decode_streaming(
+    bytes: &[u8],
+    options: DecodeOptions,
+    mut sink: impl FnMut(JsonItem) -> Result<()>,
+) -> Result<JsonDecode> {
+    if options.report_id.is_some() {
+        bail!("Filtering by Report ID is not supported when streaming");
+    }
+    decode_items(bytes, &options, |_, item| sink(item))
+}
+
+/// The first line of the `--ndjson` output
+#[derive(Serialize)]
+pub struct JsonHeader {
+    pub version: String,
+    pub descriptor: JsonDescriptor,
+}
+
+impl JsonHeader {
+    pub fn new(bytes: &[u8], options: &DecodeOptions) -> JsonHeader {
+        JsonHeader {
+            version: JSON_FORMAT_VERSION.to_string(),
+            descriptor: JsonDescriptor::new(bytes, options),
+        }
+    }
+}
+
+/// The JSON Schema of the [JsonDecode], see `--print-schema`. The
+/// schema's `version` is the [JSON_FORMAT_VERSION] it describes.
+pub fn json_schema() -> schemars::schema::RootSchema {
+    let mut schema = schemars::schema_for!(JsonDecode);
+    schema.schema.metadata().title = Some("hid-json".to_string());
+    schema
+        .schema
+        .extensions
+        .insert("version".to_string(), JSON_FORMAT_VERSION.into());
+    schema
+}
+
+/// Validate the serialized [JsonDecode] against the [json_schema], see
+/// `--validate-schema`. The error lists every violation of the schema.
+#[cfg(feature = "validate-schema")]
+pub fn validate_schema(decode: &JsonDecode) -> Result<()> {
+    let schema = serde_json::to_value(json_schema())?;
+    let schema = jsonschema::JSONSchema::compile(&schema)
+        .map_err(|e| anyhow::anyhow!("Invalid JSON Schema: {e}"))?;
+    let instance = serde_json::to_value(decode)?;
+    if let Err(errors) = schema.validate(&instance) {
+        let errors: Vec<String> = errors
+            .map(|e| format!("{}: {e}", e.instance_path))
+            .collect();
+        bail!(
+            "The output does not match the JSON Schema:\n{}",
+            errors.join("\n")
+        );
+    }
+    Ok(())
+}
+
+impl JsonDescriptor {
+    fn new(bytes: &[u8], options: &DecodeOptions) -> JsonDescriptor {
+        JsonDescriptor {
+            length: bytes.len(),
+            data: if options.skip_data {
+                None
+            } else {
+                Some(bytes.to_vec())
+            },
+        }
+    }
+}
+
+/// Decode the items and pass each item with the Report ID in effect at
+/// that item to `sink`. The returned [JsonDecode] has no items.
+fn decode_items(
+    bytes: &[u8],
+    options: &DecodeOptions,
+    mut sink: impl FnMut(Option<u32>, JsonItem) -> Result<()>,
+) -> Result<JsonDecode> {
+    let descriptor = JsonDescriptor::new(bytes, options);
+
+    let mut last_usage_page: u16 = 0;
+    let mut global_state = GlobalState::default();
+    let mut collapsed_items: usize = 0;
+    let mut field_globals = FieldGlobals::default();
+    let mut report_layout = ReportLayout {
+        keep_fields: options.with_reports || options.with_report_map,
+        ..Default::default()
+    };
+    let mut local_state = LocalState::default();
+    let mut application_summary = ApplicationSummary::default();
+    let mut validation = Validation::default();
+    let mut warnings: Vec<JsonWarning> = Vec::new();
+    let mut usage_page_histogram: BTreeMap<String, usize> = BTreeMap::new();
+    // hidreport doesn't handle long items, parse those separately
+    let (short_items, long_items) = split_long_items(bytes);
+    let rdesc_items = ReportDescriptorItems::try_from(short_items.as_slice())
+        .with_context(|| parse_error_context(bytes, &short_items, &long_items))?;
+    // Each item with the Report ID in effect at that item
+    let items = rdesc_items
+        .iter()
+        .map(|rdesc_item| -> Result<Option<(Option<u32>, JsonItem)>> {
+            let item = rdesc_item.item();
+            let offset = original_offset(&long_items, rdesc_item.offset());
+            let value = item_value(item, offset)?;
+            field_globals.update(item);
+            local_state.update(item, last_usage_page);
+            validation.update(item, offset, &field_globals, &mut warnings);
+            if let Some(report_type) = JsonReportType::lookup(item) {
+                if !is_constant_field(item) {
+                    if let Some(message) = field_globals.check_logical_range() {
+                        warnings.push(JsonWarning { offset, message });
+                    }
+                }
+                let bit_offset = report_layout.add_field(
+                    report_type,
+                    item,
+                    offset,
+                    &field_globals,
+                    &local_state,
+                    &mut warnings,
+                );
+                if options.warn_unaligned_fields {
+                    if let Some(message) = check_alignment(item, &field_globals, bit_offset) {
+                        warnings.push(JsonWarning { offset, message });
+                    }
+                }
+            }
+            application_summary.update(item, offset, &field_globals, &local_state);
+            report_layout.update_collections(item, &local_state);
+            if let ItemType::Main(_) = item.item_type() {
+                local_state = LocalState::default();
+            }
+            let global_change = global_state.update(item, value);
+            if options.collapse_redundant && global_change.as_ref().is_some_and(|c| c.redundant) {
+                collapsed_items += 1;
+                return Ok(None);
+            }
+            let item_type = match item.item_type() {
+                ItemType::Main(_) => JsonItemType::Main,
+                ItemType::Global(_) => JsonItemType::Global,
+                ItemType::Local(_) => JsonItemType::Local,
+                _ => JsonItemType::Unknown,
+            };
+            let item_name = JsonItemName::from(item);
+
+            if let ItemType::Global(GlobalItem::UsagePage { usage_page }) = item.item_type() {
+                last_usage_page = u16::from(usage_page);
+            }
+            // The Usage Page a usage item resolves against
+            let item_usage_page = match item.item_type() {
+                ItemType::Global(GlobalItem::UsagePage { .. }) => Some(last_usage_page),
+                _ => extended_usage(item, last_usage_page).map(|(page, _)| page),
+            };
+            if let Some(page) = item_usage_page {
+                let name = hut::UsagePage::try_from(page)
+                    .map(|up| format!("{up}"))
+                    .unwrap_or_else(|_| format!("{page:#06x}"));
+                *usage_page_histogram.entry(name).or_default() += 1;
+            }
+            // and now all the fields with a custom value
+            let collection = JsonCollection::lookup(item);
+            let usage_page = match item.item_type() {
+                ItemType::Global(GlobalItem::UsagePage { .. }) => lookup_usage_page(item),
+                _ => item_usage_page.and_then(|page| hut::UsagePage::try_from(page).ok()),
+            }
+            .map(|up| format!("{up}"));
+            let usage = lookup_usage(item, last_usage_page).map(|u| format!("{u}"));
+            let usage_minimum =
+                lookup_usage_limit(item, last_usage_page, true).map(|u| format!("{u}"));
+            let usage_maximum =
+                lookup_usage_limit(item, last_usage_page, false).map(|u| format!("{u}"));
+            let (value_signed, value_unsigned) = match item.item_type() {
+                ItemType::Global(_) if options.with_both_signs => (
+                    signed_value(item),
+                    item.data().and_then(|data| u32::try_from(&data).ok()),
+                ),
+                _ => (None, None),
+            };
+            // The resolution is only meaningful for fields with a unit
+            let (resolution, resolution_unit) =
+                match (JsonReportType::lookup(item), field_globals.unit) {
+                    (Some(_), Some(unit)) if unit != 0 && !is_constant_field(item) => (
+                        field_globals.resolution(),
+                        JsonUnit::from_value(unit).name(),
+                    ),
+                    _ => (None, None),
+                };
+            let state_change = if options.with_state_diff {
+                global_change.map(|c| JsonStateChange {
+                    from: c.previous,
+                    to: c.current,
+                })
+            } else {
+                None
+            };
+
+            let item = JsonItem {
+                offset,
+                data: if options.skip_data {
+                    None
+                } else {
+                    Some(item.bytes().to_owned().to_vec())
+                },
+                item_type,
+                item_name,
+                value,
+                collection,
+                usage_page,
+                usage,
+                usage_minimum,
+                usage_maximum,
+                state_change,
+                value_signed,
+                value_unsigned,
+                flags: JsonMainItemFlags::lookup(item),
+                unit: JsonUnit::lookup(item),
+                resolution,
+                resolution_unit,
+                long_item: None,
+                debug: if options.debug {
+                    Some(JsonItemDebug::new(item.bytes(), value))
+                } else {
+                    None
+                },
+            };
+            Ok(Some((field_globals.report_id, item)))
+        })
+        .filter_map(Result::transpose);
+    // The long items are inserted between the short items by offset
+    let long_item = |long_item: &LongItem| JsonItem {
+        offset: long_item.offset,
+        data: if options.skip_data {
+            None
+        } else {
+            Some(long_item.bytes.clone())
+        },
+        item_type: JsonItemType::Long,
+        item_name: JsonItemName::LongItem,
+        long_item: Some(JsonLongItem {
+            tag: long_item.bytes[2],
+            data: long_item.bytes[3..].to_vec(),
+        }),
+        debug: if options.debug {
+            Some(JsonItemDebug::new(&long_item.bytes, None))
+        } else {
+            None
+        },
+        ..Default::default()
+    };
+    let mut long_items = long_items.iter().peekable();
+    let mut last_report_id = None;
+    for decoded in items {
+        let (report_id, item) = decoded?;
+        while let Some(long) = long_items.next_if(|long| long.offset < item.offset) {
+            sink(last_report_id, long_item(long))?;
+        }
+        last_report_id = report_id;
+        sink(report_id, item)?;
+    }
+    for long in long_items {
+        sink(last_report_id, long_item(long))?;
+    }
+    validation.finish(&mut warnings);
+    warnings.sort_by_key(|warning| warning.offset);
+
+    Ok(JsonDecode {
+        version: JSON_FORMAT_VERSION.to_string(),
+        descriptor,
+        items: Vec::new(),
+        collapsed_items: if options.collapse_redundant {
+            Some(collapsed_items)
+        } else {
+            None
+        },
+        warnings,
+        summary: if options.summary {
+            Some(JsonSummary {
+                usage_page_histogram,
+                applications: application_summary.applications,
+            })
+        } else {
+            None
+        },
+        reports: if options.with_reports {
+            Some(report_layout.reports())
+        } else {
+            None
+        },
+        layout: report_layout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failing_item() {
+        let bytes = [
+            0x05, 0x01, // Usage Page (Generic Desktop)
+            0x09, 0x02, // Usage (Mouse)
+            0xf0, 0x00, // Invalid
+            0xa1, 0x01, // Collection (Application)
+            0xc0, // End Collection
+        ];
+        let parses = |items: &[u8]| !items.contains(&0xf0);
+        assert_eq!(failing_item_offset(&bytes, parses), Some(4));
+        assert_eq!(failing_item_offset(&bytes[..4], parses), None);
+        // A truncated item
+        assert_eq!(
+            failing_item_offset(&bytes[..3], |items| items.len() != 3),
+            Some(2)
+        );
+    }
+}
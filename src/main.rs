@@ -1,25 +1,54 @@
 // SPDX-License-Identifier: MIT
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use base64::Engine;
 use clap::{Parser, ValueEnum};
-use hidreport::hid::*;
-use serde::Serialize;
-use std::io::Write;
+use hid_json::{
+    decode, decode_streaming, leading_report_id, DecodeOptions, JsonDecode, JsonHeader,
+    JsonReportMapWithDescriptor,
+};
+use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::process::ExitCode;
 
 #[derive(Clone, Debug, ValueEnum)]
 enum Format {
     JsonV1,
+    /// Like json-v1 but with the items nested in their collections
+    JsonTree,
+    /// Like json-v1 but as YAML
+    Yaml,
+    /// One line per item with the item bytes and a description
+    Text,
+    /// A minimal description of each report and its fields, e.g. for
+    /// prototyping with WebHID
+    ReportMap,
+    /// Add the decoded report descriptor to the SQLite database
+    /// given as --output-file
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum InputFormat {
+    /// The raw report descriptor bytes
+    Binary,
+    /// Hexadecimal bytes separated by whitespace or commas,
+    /// optionally prefixed with 0x
+    Hex,
+    /// Base64-encoded bytes
+    Base64,
 }
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// Print debugging information
+    /// Print debugging information. This includes a `debug`
+    /// object with the low-level item details for each item.
     #[arg(short, long, default_value_t = false)]
     debug: bool,
 
+    /// Pretty-print the JSON output. Ignored for --format yaml.
     #[arg(long, default_value_t = false)]
     pretty: bool,
 
@@ -35,268 +64,383 @@ struct Cli {
     #[arg(long, default_value_t = ("-").to_string())]
     output_file: String,
 
+    /// Create any missing parent directories of the --output-file
+    #[arg(long, default_value_t = false)]
+    create_dirs: bool,
+
     #[arg(long, value_enum, default_value_t = Format::JsonV1)]
     format: Format,
 
-    /// Path to a hid report descriptor file
-    path: PathBuf,
-}
+    /// Omit Global items that re-declare the value already in
+    /// effect, e.g. the same UsagePage twice in a row. The number
+    /// of omitted items is included in the JSON output.
+    ///
+    /// Values declared before a Push or Pop item are never
+    /// considered when looking for redundant items.
+    #[arg(long, default_value_t = false)]
+    collapse_redundant: bool,
 
-// Warning: These enum value names are JSON API
-#[derive(Serialize)]
-enum JsonItemType {
-    Global,
-    Main,
-    Local,
-    Unknown,
-}
+    /// Drop the first byte of the file before decoding.
+    ///
+    /// Reports read from a hidraw device start with the report ID,
+    /// use this if the file was accidentally captured with such a
+    /// leading byte.
+    #[arg(long, default_value_t = false)]
+    strip_report_id_byte: bool,
+
+    /// For each Global item, include the value in effect before
+    /// and after that item
+    #[arg(long, default_value_t = false)]
+    with_state_diff: bool,
+
+    /// Warn about multi-byte variable fields that don't start on
+    /// a byte boundary
+    #[arg(long, default_value_t = false)]
+    warn_unaligned_fields: bool,
+
+    /// For each Global item, include the item data as both signed
+    /// and unsigned value
+    #[arg(long, default_value_t = false)]
+    with_both_signs: bool,
+
+    /// Only output the JSON array of items, without the surrounding
+    /// version and descriptor information
+    #[arg(long, default_value_t = false)]
+    items_only: bool,
+
+    /// Include a summary of the report descriptor in the JSON output
+    #[arg(long, default_value_t = false)]
+    summary: bool,
 
-// Warning: These enum value names are JSON API
-#[derive(Serialize)]
-enum JsonItemName {
-    Unknown,
-    Input,
-    Output,
-    Feature,
-    Collection,
-    EndCollection,
-    UsagePage,
-    LogicalMinimum,
-    LogicalMaximum,
-    PhysicalMinimum,
-    PhysicalMaximum,
-    UnitExponent,
-    Unit,
-    ReportSize,
-    ReportId,
-    ReportCount,
-    Push,
-    Pop,
-    Reserved,
-    Usage,
-    UsageMinimum,
-    UsageMaximum,
-    DesignatorIndex,
-    DesignatorMinimum,
-    DesignatorMaximum,
-    StringIndex,
-    StringMinimum,
-    StringMaximum,
-    Delimiter,
+    /// Include the fields of each report with their bit offsets
+    /// in the JSON output
+    #[arg(long, default_value_t = false)]
+    with_reports: bool,
+
+    /// Only output the items of the report with this Report ID, see
+    /// JSON_FORMAT.md for details
+    #[arg(long)]
+    report_id: Option<u32>,
+
+    /// With --format report-map, include the report descriptor length and
+    /// data alongside the reports
+    #[arg(long, default_value_t = false)]
+    include_raw_descriptor_in_reports: bool,
+
+    /// Convert a JSON document in the format of this tool back into
+    /// the bytes of a report descriptor. Only the name and value of
+    /// each item are used.
+    #[arg(long, default_value_t = false)]
+    encode: bool,
+
+    /// Write newline-delimited JSON: the version and descriptor on the
+    /// first line, followed by one line per item, written as each item
+    /// is decoded. The optional top-level fields are not included.
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["format", "report_id", "items_only"]
+    )]
+    ndjson: bool,
+
+    /// Exit with an error if the report descriptor has any warnings,
+    /// after writing the output
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// The format of the report descriptor file
+    #[arg(long, value_enum, default_value_t = InputFormat::Binary)]
+    input_format: InputFormat,
+
+    /// Read the report descriptor of the given hidraw device, e.g.
+    /// `hidraw0` or `/dev/hidraw0`, from sysfs. Linux only.
+    #[arg(long, conflicts_with = "path")]
+    device: Option<String>,
+
+    /// Print the JSON Schema of the JSON output format and exit. The
+    /// schema's version matches the version field of the output.
+    #[arg(long, default_value_t = false)]
+    print_schema: bool,
+
+    /// Validate the output against the JSON Schema of --print-schema
+    /// before writing it and exit with an error on any mismatch. The
+    /// schema only describes the default --format json-v1 output.
+    #[cfg(feature = "validate-schema")]
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["ndjson", "items_only"]
+    )]
+    validate_schema: bool,
+
+    /// Path to a hid report descriptor file or `-` for stdin
+    #[arg(required_unless_present_any = ["device", "print_schema"])]
+    path: Option<PathBuf>,
 }
 
-impl<T> From<&T> for JsonItemName
-    where T: Item,
-{
-    fn from(item: &T) -> JsonItemName {
-        match item.item_type() {
-            ItemType::Main(mi) => {
-                match mi {
-                    MainItem::Input(_) => JsonItemName::Input,
-                    MainItem::Output(_) => JsonItemName::Output,
-                    MainItem::Feature(_) => JsonItemName::Feature,
-                    MainItem::Collection(_) => JsonItemName::Collection,
-                    MainItem::EndCollection => JsonItemName::EndCollection,
-                }
-            }, 
-            ItemType::Global(gi) => {
-                match gi {
-                    GlobalItem::UsagePage { .. } => JsonItemName::UsagePage,
-                    GlobalItem::LogicalMinimum { .. } => JsonItemName::LogicalMinimum,
-                    GlobalItem::LogicalMaximum { .. } => JsonItemName::LogicalMaximum,
-                    GlobalItem::PhysicalMinimum { .. } => JsonItemName::PhysicalMinimum,
-                    GlobalItem::PhysicalMaximum { .. } => JsonItemName::PhysicalMaximum,
-                    GlobalItem::UnitExponent { .. } => JsonItemName::UnitExponent,
-                    GlobalItem::Unit { .. } => JsonItemName::Unit,
-                    GlobalItem::ReportSize { .. } => JsonItemName::ReportSize,
-                    GlobalItem::ReportId { .. } => JsonItemName::ReportId,
-                    GlobalItem::ReportCount { .. } => JsonItemName::ReportCount,
-                    GlobalItem::Push => JsonItemName::Push,
-                    GlobalItem::Pop => JsonItemName::Pop,
-                    GlobalItem::Reserved => JsonItemName::Reserved,
-                }
-            },
-            ItemType::Local(li) => {
-                match li {
-                    LocalItem::Usage { .. } => JsonItemName::Usage,
-                    LocalItem::UsageMinimum { .. } => JsonItemName::UsageMinimum,
-                    LocalItem::UsageMaximum { .. } => JsonItemName::UsageMaximum,
-                    LocalItem::DesignatorIndex { .. } => JsonItemName::DesignatorIndex,
-                    LocalItem::DesignatorMinimum { .. } => JsonItemName::DesignatorMinimum,
-                    LocalItem::DesignatorMaximum { .. } => JsonItemName::DesignatorMaximum,
-                    LocalItem::StringIndex { .. } => JsonItemName::StringIndex,
-                    LocalItem::StringMinimum { .. } => JsonItemName::StringMinimum,
-                    LocalItem::StringMaximum { .. } => JsonItemName::StringMaximum,
-                    LocalItem::Delimiter { .. } => JsonItemName::Delimiter,
-                    LocalItem::Reserved { .. } => JsonItemName::Reserved,
-                }
-            },
-            _ => JsonItemName::Unknown,
+/// Parse a hex dump like `0x05, 0x01, 0x09, 0x02` or `05 01 09 02`.
+/// A token may contain multiple bytes, e.g. `05010902`.
+fn parse_hex(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for token in text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+    {
+        let digits = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if digits.is_empty()
+            || !digits.chars().all(|c| c.is_ascii_hexdigit())
+            || (digits.len() > 1 && digits.len() % 2 != 0)
+        {
+            bail!("Invalid hex byte '{token}'");
+        }
+        for pair in digits.as_bytes().chunks(2) {
+            // Only ASCII hex digits at this point
+            let pair = std::str::from_utf8(pair)?;
+            bytes.push(u8::from_str_radix(pair, 16)?);
         }
     }
+    Ok(bytes)
 }
 
-// Warning: These enum value names are JSON API
-#[derive(Serialize)]
-enum JsonCollection {
-    Physical,
-    Logical,
-    Application,
+fn parse_base64(text: &str) -> Result<Vec<u8>> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    base64::engine::general_purpose::STANDARD
+        .decode(text)
+        .context("Invalid base64 data")
 }
 
-impl JsonCollection {
-    // can't implement try_from generically, so let's do this instead
-    fn lookup(item: &impl Item) -> Option<JsonCollection> {
-        match item.item_type() {
-            ItemType::Main(MainItem::Collection(c)) => Some(
-                match c {
-                    CollectionItem::Physical => JsonCollection::Physical,
-                    CollectionItem::Logical => JsonCollection::Logical,
-                    CollectionItem::Application => JsonCollection::Application,
-                    _  => todo!()
-                }),
-            _ => None,
-        }
-    }
+/// The sysfs path of the report descriptor of the given hidraw device
+#[cfg(target_os = "linux")]
+fn device_path(device: &str) -> Result<PathBuf> {
+    let name = std::path::Path::new(device)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.starts_with("hidraw"))
+        .with_context(|| format!("Invalid hidraw device '{device}'"))?;
+    Ok(PathBuf::from("/sys/class/hidraw")
+        .join(name)
+        .join("device/report_descriptor"))
 }
 
-#[derive(Serialize)]
-struct JsonDescriptor {
-    length: usize,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<Vec<u8>>,
+#[cfg(not(target_os = "linux"))]
+fn device_path(_device: &str) -> Result<PathBuf> {
+    bail!("--device is only supported on Linux")
 }
 
-#[derive(Serialize)]
-struct JsonItem {
-    offset: usize,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<Vec<u8>>,
-    #[serde(rename = "type")]
-    item_type: JsonItemType,
-    #[serde(rename = "name")]
-    item_name: JsonItemName,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    value: Option<i32>,
-
-    // Very optional fields
-    #[serde(skip_serializing_if = "Option::is_none")]
-    collection: Option<JsonCollection>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    usage_page: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    usage: Option<String>,
+/// Read the whole file. sysfs files have a size of 0 so this must
+/// not rely on the file size.
+fn read_file(path: &std::path::Path) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut bytes))
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(bytes)
 }
 
-#[derive(Serialize)]
-struct JsonDecode<'a> {
-    version: &'a str,
-    descriptor: JsonDescriptor,
-    items: Vec<JsonItem>,
+/// The path of the --output-file or `None` for stdout. With --create-dirs,
+/// any missing parent directories are created.
+fn output_path(cli: &Cli) -> Result<Option<PathBuf>> {
+    if cli.output_file == "-" {
+        return Ok(None);
+    }
+    let path = PathBuf::from(&cli.output_file);
+    if cli.create_dirs {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+    }
+    Ok(Some(path))
 }
 
-fn lookup_usage_page(item: &impl Item) -> Option<hut::UsagePage> {
-    match item.item_type() {
-        ItemType::Global(GlobalItem::UsagePage { .. }) => {
-            if let Some(data) = item.data() {
-                let up: u16 = u32::try_from(&data).unwrap() as u16;
-                hut::UsagePage::try_from(up).ok()
-            } else {
-                None
-            }
-        },
-        _ => None,
-    }
+fn open_output(cli: &Cli) -> Result<Box<dyn Write>> {
+    Ok(match output_path(cli)? {
+        None => Box::new(std::io::stdout()),
+        Some(path) => Box::new(
+            std::fs::File::create(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?,
+        ),
+    })
 }
 
-fn lookup_usage(item: &impl Item, usage_page: u16) -> Option<hut::Usage> {
-    match item.item_type() {
-        ItemType::Local(LocalItem::Usage { .. }) => {
-            if let Some(data) = item.data() {
-                let u: u16 = u32::try_from(&data).unwrap() as u16;
-                hut::Usage::new_from_page_and_id(usage_page, u).ok()
-            } else {
-                None
-            }
-        },
-        _ => None,
+/// Whether the output format needs the fields of each report
+fn needs_report_map(format: &Format) -> bool {
+    match format {
+        Format::ReportMap => true,
+        #[cfg(feature = "sqlite")]
+        Format::Sqlite => true,
+        _ => false,
     }
 }
 
+/// Decode the report descriptor while writing the `--ndjson` output
+fn write_ndjson(cli: &Cli, bytes: &[u8], options: DecodeOptions) -> Result<JsonDecode> {
+    let mut stream = open_output(cli)?;
+    serde_json::to_writer(&mut stream, &JsonHeader::new(bytes, &options))?;
+    writeln!(stream)?;
+    decode_streaming(bytes, options, |item| {
+        serde_json::to_writer(&mut stream, &item)?;
+        writeln!(stream)?;
+        Ok(())
+    })
+}
+
 fn hid_decode() -> Result<()> {
     let cli = Cli::parse();
 
-    let stream: Box<dyn Write> = if cli.output_file == "-" {
-        Box::new(std::io::stdout())
+    if cli.print_schema {
+        let stream = open_output(&cli)?;
+        serde_json::to_writer_pretty(stream, &hid_json::json_schema())?;
+        return Ok(());
+    }
+
+    let path = match (&cli.device, &cli.path) {
+        (Some(device), _) => device_path(device)?,
+        (None, Some(path)) => path.clone(),
+        (None, None) => bail!("Missing path to a report descriptor"),
+    };
+    let mut bytes = if path.as_os_str() == "-" {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("Failed to read from stdin")?;
+        bytes
     } else {
-        Box::new(std::fs::File::create(cli.output_file).unwrap())
+        read_file(&path)?
     };
 
-    let bytes = std::fs::read(&cli.path)?;
+    if cli.include_raw_descriptor_in_reports && !matches!(cli.format, Format::ReportMap) {
+        bail!("--include-raw-descriptor-in-reports requires --format report-map");
+    }
+    if cli.skip_data && matches!(cli.format, Format::Text) {
+        bail!("--skip-data cannot be used with --format text, the text lists the item bytes");
+    }
+    #[cfg(feature = "validate-schema")]
+    if cli.validate_schema && !matches!(cli.format, Format::JsonV1) {
+        bail!("--validate-schema requires --format json-v1");
+    }
 
-    let version = "1.0";
-    let descriptor = JsonDescriptor {
-        length: bytes.len(),
-        data: if cli.skip_data {
-            None
-        } else {
-            Some(bytes.to_vec())
-        },
+    if cli.encode {
+        let rdesc = hid_json::encode::from_json(&bytes)?;
+        open_output(&cli)?.write_all(&rdesc)?;
+        return Ok(());
+    }
+
+    bytes = match cli.input_format {
+        InputFormat::Binary => bytes,
+        InputFormat::Hex => parse_hex(std::str::from_utf8(&bytes)?)?,
+        InputFormat::Base64 => parse_base64(std::str::from_utf8(&bytes)?)?,
     };
+    if cli.strip_report_id_byte && !bytes.is_empty() {
+        bytes.remove(0);
+    }
 
-    let mut last_usage_page: u16 = 0;
-    let rdesc_items = ReportDescriptorItems::try_from(bytes.as_slice())?;
-    let items = rdesc_items
-        .iter()
-        .map(|rdesc_item| {
-            let item = rdesc_item.item();
-            let offset = rdesc_item.offset();
-            let item_type = match item.item_type() {
-                ItemType::Main(_) => JsonItemType::Main,
-                ItemType::Global(_) => JsonItemType::Global,
-                ItemType::Local(_) => JsonItemType::Local,    
-                _ => JsonItemType::Unknown,
-            };
-            let item_name = JsonItemName::from(item);
-            let value = match item.data() {
-                None => None,
-                Some(data) => Some(u32::try_from(&data).unwrap() as i32),
-            };
+    let options = DecodeOptions::default()
+        .skip_data(cli.skip_data)
+        .debug(cli.debug)
+        .collapse_redundant(cli.collapse_redundant)
+        .with_state_diff(cli.with_state_diff)
+        .with_both_signs(cli.with_both_signs)
+        .warn_unaligned_fields(cli.warn_unaligned_fields)
+        .summary(cli.summary)
+        .with_reports(cli.with_reports)
+        .with_report_map(needs_report_map(&cli.format))
+        .report_id(cli.report_id);
+    let result = if cli.ndjson {
+        write_ndjson(&cli, &bytes, options)
+    } else {
+        decode(&bytes, options)
+    };
+    let decode = match result {
+        Ok(decode) => decode,
+        Err(e) => {
+            if let Some(report_id) = leading_report_id(&bytes) {
+                return Err(e.context(format!(
+                    "The first byte {report_id:#04x} matches a Report ID declared in the remaining \
+                     bytes, this looks like a report ID byte and not a report descriptor. \
+                     Try again with --strip-report-id-byte"
+                )));
+            }
+            return Err(e);
+        }
+    };
+
+    #[cfg(feature = "validate-schema")]
+    if cli.validate_schema {
+        hid_json::validate_schema(&decode)?;
+    }
 
-            if let ItemType::Global(GlobalItem::UsagePage { usage_page }) = item.item_type() {
-                last_usage_page = u16::from(usage_page);
+    match cli.format {
+        // Already written while decoding
+        _ if cli.ndjson => {}
+        Format::JsonV1 => {
+            let stream = open_output(&cli)?;
+            match (cli.items_only, cli.skip_data || cli.pretty) {
+                (true, true) => serde_json::to_writer_pretty(stream, &decode.items)?,
+                (true, false) => serde_json::to_writer(stream, &decode.items)?,
+                (false, true) => serde_json::to_writer_pretty(stream, &decode)?,
+                (false, false) => serde_json::to_writer(stream, &decode)?,
             }
-            // and now all the fields with a custom value
-            let collection = JsonCollection::lookup(item);
-            let usage_page = lookup_usage_page(item).map(|up| format!("{up}"));
-            let usage = lookup_usage(item, last_usage_page).map(|u| format!("{u}"));
-
-            JsonItem {
-                offset,
-                data: if cli.skip_data {
-                    None
-                } else {
-                    Some(item.bytes().to_owned().to_vec())
-                },
-                item_type,
-                item_name,
-                value,
-                collection,
-                usage_page,
-                usage,
+        }
+        Format::JsonTree => {
+            let stream = open_output(&cli)?;
+            let tree = decode.tree()?;
+            match (cli.items_only, cli.skip_data || cli.pretty) {
+                (true, true) => serde_json::to_writer_pretty(stream, &tree.items)?,
+                (true, false) => serde_json::to_writer(stream, &tree.items)?,
+                (false, true) => serde_json::to_writer_pretty(stream, &tree)?,
+                (false, false) => serde_json::to_writer(stream, &tree)?,
+            }
+        }
+        Format::Yaml => {
+            let stream = open_output(&cli)?;
+            // serde_yaml writes enum variants with a value as YAML tags
+            // (`!VendorDefined 130`), going through the JSON value keeps
+            // the structure of the JSON output
+            let value = if cli.items_only {
+                serde_json::to_value(&decode.items)?
+            } else {
+                serde_json::to_value(&decode)?
+            };
+            serde_yaml::to_writer(stream, &value)?;
+        }
+        Format::Text => {
+            let mut stream = open_output(&cli)?;
+            stream.write_all(hid_json::text::to_text(&decode.items).as_bytes())?;
+        }
+        Format::ReportMap => {
+            let stream = open_output(&cli)?;
+            let report_map = decode.report_map();
+            let with_descriptor = JsonReportMapWithDescriptor {
+                descriptor: &decode.descriptor,
+                reports: &report_map,
+            };
+            match (cli.include_raw_descriptor_in_reports, cli.pretty) {
+                (true, true) => serde_json::to_writer_pretty(stream, &with_descriptor)?,
+                (true, false) => serde_json::to_writer(stream, &with_descriptor)?,
+                (false, true) => serde_json::to_writer_pretty(stream, &report_map)?,
+                (false, false) => serde_json::to_writer(stream, &report_map)?,
             }
-        })
-        .collect::<Vec<JsonItem>>();
+        }
+        #[cfg(feature = "sqlite")]
+        Format::Sqlite => {
+            let Some(db_path) = output_path(&cli)? else {
+                bail!("--format sqlite requires an --output-file");
+            };
+            let source = path.display().to_string();
+            hid_json::sqlite::export(&db_path, &source, &bytes, &decode)?;
+        }
+    }
 
-    let decode = JsonDecode {
-        version,
-        descriptor,
-        items,
-    };
-    if cli.skip_data || cli.pretty {
-        serde_json::to_writer_pretty(stream, &decode)?;
-    } else {
-        serde_json::to_writer(stream, &decode)?;
+    if cli.strict && !decode.warnings.is_empty() {
+        for warning in &decode.warnings {
+            eprintln!("Warning: offset {}: {}", warning.offset, warning.message);
+        }
+        let count = decode.warnings.len();
+        bail!("{count} warnings in the report descriptor");
     }
 
     Ok(())
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: MIT
+
+//! Export of a decoded report descriptor into an SQLite database.
+//!
+//! A database may hold any number of report descriptors. Each report
+//! descriptor is identified by its data bytes, adding the same report
+//! descriptor again replaces its items, reports and fields.
+
+use crate::JsonDecode;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS descriptors (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL,
+    length INTEGER NOT NULL,
+    data BLOB NOT NULL UNIQUE
+);
+CREATE TABLE IF NOT EXISTS items (
+    descriptor_id INTEGER NOT NULL REFERENCES descriptors (id),
+    byte_offset INTEGER NOT NULL,
+    item_type TEXT NOT NULL,
+    item_name TEXT NOT NULL,
+    value INTEGER,
+    data BLOB,
+    usage_page TEXT,
+    usage TEXT,
+    PRIMARY KEY (descriptor_id, byte_offset)
+);
+CREATE TABLE IF NOT EXISTS reports (
+    descriptor_id INTEGER NOT NULL REFERENCES descriptors (id),
+    report_id INTEGER NOT NULL,
+    report_type TEXT NOT NULL,
+    length INTEGER NOT NULL,
+    PRIMARY KEY (descriptor_id, report_id, report_type)
+);
+CREATE TABLE IF NOT EXISTS fields (
+    descriptor_id INTEGER NOT NULL REFERENCES descriptors (id),
+    report_id INTEGER NOT NULL,
+    report_type TEXT NOT NULL,
+    bit_offset INTEGER NOT NULL,
+    bits INTEGER NOT NULL,
+    signed INTEGER NOT NULL,
+    usage INTEGER,
+    usage_name TEXT
+);
+";
+
+/// Add the report descriptor to the database at `path`, creating the
+/// database if needed. `source` is the path the report descriptor was
+/// read from. The `decode` must be decoded with
+/// [crate::DecodeOptions::with_report_map] or
+/// [crate::DecodeOptions::with_reports] to include the fields.
+pub fn export(path: &Path, source: &str, bytes: &[u8], decode: &JsonDecode) -> Result<()> {
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("Failed to open database {}", path.display()))?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let descriptor_id: i64 = tx.query_row(
+        "INSERT INTO descriptors (path, length, data) VALUES (?1, ?2, ?3)
+         ON CONFLICT (data) DO UPDATE SET path = excluded.path
+         RETURNING id",
+        params![source, bytes.len() as i64, bytes],
+        |row| row.get(0),
+    )?;
+    for table in ["items", "reports", "fields"] {
+        tx.execute(
+            &format!("DELETE FROM {table} WHERE descriptor_id = ?1"),
+            params![descriptor_id],
+        )?;
+    }
+
+    for item in &decode.items {
+        tx.execute(
+            "INSERT INTO items
+             (descriptor_id, byte_offset, item_type, item_name, value, data, usage_page, usage)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                descriptor_id,
+                item.offset as i64,
+                format!("{:?}", item.item_type),
+                format!("{:?}", item.item_name),
+                item.value,
+                item.data,
+                item.usage_page,
+                item.usage,
+            ],
+        )?;
+    }
+
+    for report in &decode.layout.reports {
+        let report_type = format!("{:?}", report.report_type);
+        tx.execute(
+            "INSERT INTO reports (descriptor_id, report_id, report_type, length)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                descriptor_id,
+                report.report_id,
+                report_type,
+                report.length()
+            ],
+        )?;
+        let elements = report.fields.iter().flat_map(|field| {
+            (0..)
+                .zip(field.elements())
+                .map(|(idx, element)| (field.element_offset(idx), element))
+        });
+        for (bit_offset, element) in elements {
+            tx.execute(
+                "INSERT INTO fields
+                 (descriptor_id, report_id, report_type, bit_offset, bits, signed, usage, usage_name)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    descriptor_id,
+                    report.report_id,
+                    report_type,
+                    bit_offset,
+                    element.bits,
+                    element.signed,
+                    element.usage,
+                    element.usage.map(crate::usage_name),
+                ],
+            )?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: MIT
+
+//! A human-readable description of the items, one line per item in the
+//! style of the kernel and other HID tools, see `--format text`:
+//!
+//! ```text
+//! 0x05, 0x01,                    // Usage Page (Generic Desktop)        0
+//! 0x09, 0x02,                    // Usage (Mouse)                       2
+//! 0xa1, 0x01,                    // Collection (Application)            4
+//! 0x85, 0x01,                    //   Report ID (1)                     6
+//! ```
+
+use crate::{JsonCollection, JsonItem, JsonItemName, JsonMainItemFlags};
+
+/// One line per item, indented by collection depth. The bytes of each
+/// item are taken from its `data`, i.e. the items must be decoded without
+/// [crate::DecodeOptions::skip_data].
+pub fn to_text(items: &[JsonItem]) -> String {
+    let mut text = String::new();
+    let mut depth: usize = 0;
+    for item in items {
+        if let JsonItemName::EndCollection = item.item_name {
+            depth = depth.saturating_sub(1);
+        }
+        let bytes = item
+            .data
+            .iter()
+            .flatten()
+            .map(|b| format!("{b:#04x}, "))
+            .collect::<String>();
+        let comment = format!("{}{}", "  ".repeat(depth), describe(item));
+        text.push_str(&format!(
+            "{:<30} // {comment:<35} {}\n",
+            bytes.trim_end(),
+            item.offset
+        ));
+        if let JsonItemName::Collection = item.item_name {
+            depth += 1;
+        }
+    }
+    text
+}
+
+fn describe(item: &JsonItem) -> String {
+    let name = item_label(&item.item_name);
+    let value = || item.value.map(|v| v.to_string());
+    let hex = || item.value.map(|v| format!("{v:#x}"));
+    let argument = match item.item_name {
+        JsonItemName::UsagePage => item.usage_page.clone().or_else(hex),
+        JsonItemName::Usage => item.usage.clone().or_else(hex),
+        JsonItemName::UsageMinimum => item.usage_minimum.clone().or_else(hex),
+        JsonItemName::UsageMaximum => item.usage_maximum.clone().or_else(hex),
+        JsonItemName::Collection => item.collection.as_ref().map(collection_label),
+        JsonItemName::Input | JsonItemName::Output | JsonItemName::Feature => {
+            item.flags.as_ref().map(flags_label)
+        }
+        JsonItemName::Unit => item.unit.as_ref().and_then(|unit| unit.name()).or_else(hex),
+        JsonItemName::LongItem => item
+            .long_item
+            .as_ref()
+            .map(|long_item| format!("tag {:#04x}", long_item.tag)),
+        _ => value(),
+    };
+    match argument {
+        Some(argument) => format!("{name} ({argument})"),
+        None => name.to_string(),
+    }
+}
+
+fn item_label(item_name: &JsonItemName) -> &'static str {
+    match item_name {
+        JsonItemName::Unknown => "Unknown",
+        JsonItemName::Input => "Input",
+        JsonItemName::Output => "Output",
+        JsonItemName::Feature => "Feature",
+        JsonItemName::Collection => "Collection",
+        JsonItemName::EndCollection => "End Collection",
+        JsonItemName::UsagePage => "Usage Page",
+        JsonItemName::LogicalMinimum => "Logical Minimum",
+        JsonItemName::LogicalMaximum => "Logical Maximum",
+        JsonItemName::PhysicalMinimum => "Physical Minimum",
+        JsonItemName::PhysicalMaximum => "Physical Maximum",
+        JsonItemName::UnitExponent => "Unit Exponent",
+        JsonItemName::Unit => "Unit",
+        JsonItemName::ReportSize => "Report Size",
+        JsonItemName::ReportId => "Report ID",
+        JsonItemName::ReportCount => "Report Count",
+        JsonItemName::Push => "Push",
+        JsonItemName::Pop => "Pop",
+        JsonItemName::Reserved => "Reserved",
+        JsonItemName::Usage => "Usage",
+        JsonItemName::UsageMinimum => "Usage Minimum",
+        JsonItemName::UsageMaximum => "Usage Maximum",
+        JsonItemName::DesignatorIndex => "Designator Index",
+        JsonItemName::DesignatorMinimum => "Designator Minimum",
+        JsonItemName::DesignatorMaximum => "Designator Maximum",
+        JsonItemName::StringIndex => "String Index",
+        JsonItemName::StringMinimum => "String Minimum",
+        JsonItemName::StringMaximum => "String Maximum",
+        JsonItemName::Delimiter => "Delimiter",
+        JsonItemName::LongItem => "Long Item",
+    }
+}
+
+fn collection_label(collection: &JsonCollection) -> String {
+    match collection {
+        JsonCollection::NamedArray => "Named Array".to_string(),
+        JsonCollection::UsageSwitch => "Usage Switch".to_string(),
+        JsonCollection::UsageModifier => "Usage Modifier".to_string(),
+        JsonCollection::VendorDefined(value) => format!("Vendor Defined {value:#04x}"),
+        collection => format!("{collection:?}"),
+    }
+}
+
+/// The flags in the abbreviated form of the HID Specification, e.g.
+/// `Data,Var,Abs`. Flags other than the first three are only listed
+/// if set.
+fn flags_label(flags: &JsonMainItemFlags) -> String {
+    let mut labels = vec![
+        if flags.constant { "Cnst" } else { "Data" },
+        if flags.variable { "Var" } else { "Arr" },
+        if flags.relative { "Rel" } else { "Abs" },
+    ];
+    for (set, label) in [
+        (flags.wrap, "Wrap"),
+        (flags.nonlinear, "NonLin"),
+        (flags.no_preferred_state, "NoPref"),
+        (flags.null_state, "Null"),
+        (flags.volatile == Some(true), "Vol"),
+        (flags.buffered_bytes, "Buff"),
+    ] {
+        if set {
+            labels.push(label);
+        }
+    }
+    labels.join(",")
+}
@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: MIT
+
+//! Report descriptors shared by the tests
+
+#![allow(dead_code)]
+
+/// The boot mouse of the HID Specification, Appendix E.10
+pub const MOUSE: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x02, // Usage (Mouse)
+    0xa1, 0x01, // Collection (Application)
+    0x09, 0x01, //   Usage (Pointer)
+    0xa1, 0x00, //   Collection (Physical)
+    0x05, 0x09, //     Usage Page (Button)
+    0x19, 0x01, //     Usage Minimum (1)
+    0x29, 0x03, //     Usage Maximum (3)
+    0x15, 0x00, //     Logical Minimum (0)
+    0x25, 0x01, //     Logical Maximum (1)
+    0x95, 0x03, //     Report Count (3)
+    0x75, 0x01, //     Report Size (1)
+    0x81, 0x02, //     Input (Data,Var,Abs)
+    0x95, 0x01, //     Report Count (1)
+    0x75, 0x05, //     Report Size (5)
+    0x81, 0x01, //     Input (Cnst,Arr,Abs)
+    0x05, 0x01, //     Usage Page (Generic Desktop)
+    0x09, 0x30, //     Usage (X)
+    0x09, 0x31, //     Usage (Y)
+    0x15, 0x81, //     Logical Minimum (-127)
+    0x25, 0x7f, //     Logical Maximum (127)
+    0x75, 0x08, //     Report Size (8)
+    0x95, 0x02, //     Report Count (2)
+    0x81, 0x06, //     Input (Data,Var,Rel)
+    0xc0, //   End Collection
+    0xc0, // End Collection
+];
+
+/// The boot keyboard of the HID Specification, Appendix E.6
+pub const KEYBOARD: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x06, // Usage (Keyboard)
+    0xa1, 0x01, // Collection (Application)
+    0x05, 0x07, //   Usage Page (Keyboard/Keypad)
+    0x19, 0xe0, //   Usage Minimum (224)
+    0x29, 0xe7, //   Usage Maximum (231)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x08, //   Report Count (8)
+    0x81, 0x02, //   Input (Data,Var,Abs)
+    0x95, 0x01, //   Report Count (1)
+    0x75, 0x08, //   Report Size (8)
+    0x81, 0x01, //   Input (Cnst,Arr,Abs)
+    0x95, 0x05, //   Report Count (5)
+    0x75, 0x01, //   Report Size (1)
+    0x05, 0x08, //   Usage Page (LED)
+    0x19, 0x01, //   Usage Minimum (1)
+    0x29, 0x05, //   Usage Maximum (5)
+    0x91, 0x02, //   Output (Data,Var,Abs)
+    0x95, 0x01, //   Report Count (1)
+    0x75, 0x03, //   Report Size (3)
+    0x91, 0x01, //   Output (Cnst,Arr,Abs)
+    0x95, 0x06, //   Report Count (6)
+    0x75, 0x08, //   Report Size (8)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x65, //   Logical Maximum (101)
+    0x05, 0x07, //   Usage Page (Keyboard/Keypad)
+    0x19, 0x00, //   Usage Minimum (0)
+    0x29, 0x65, //   Usage Maximum (101)
+    0x81, 0x00, //   Input (Data,Arr,Abs)
+    0xc0, // End Collection
+];
+
+/// A joystick that uses Push and Pop and signed Global items of all sizes
+pub const JOYSTICK: &[u8] = &[
+    0x05, 0x01, // Usage Page (Generic Desktop)
+    0x09, 0x04, // Usage (Joystick)
+    0xa1, 0x01, // Collection (Application)
+    0xa4, //   Push
+    0x15, 0x81, //     Logical Minimum (-127)
+    0x25, 0x7f, //     Logical Maximum (127)
+    0x35, 0x00, //     Physical Minimum (0)
+    0x46, 0xff, 0x00, //     Physical Maximum (255)
+    0x55, 0x0e, //     Unit Exponent (-2)
+    0x65, 0x14, //     Unit (English Rotation: deg)
+    0x09, 0x30, //     Usage (X)
+    0x75, 0x08, //     Report Size (8)
+    0x95, 0x01, //     Report Count (1)
+    0x81, 0x02, //     Input (Data,Var,Abs)
+    0x16, 0x00, 0x80, //     Logical Minimum (-32768)
+    0x26, 0xff, 0x7f, //     Logical Maximum (32767)
+    0x09, 0x31, //     Usage (Y)
+    0x75, 0x10, //     Report Size (16)
+    0x81, 0x02, //     Input (Data,Var,Abs)
+    0x17, 0x00, 0x00, 0x00, 0x80, //     Logical Minimum (-2147483648)
+    0x27, 0xff, 0xff, 0xff, 0x7f, //     Logical Maximum (2147483647)
+    0x09, 0x32, //     Usage (Z)
+    0x75, 0x20, //     Report Size (32)
+    0x81, 0x02, //     Input (Data,Var,Abs)
+    0xb4, //   Pop
+    0x05, 0x09, //   Usage Page (Button)
+    0x19, 0x01, //   Usage Minimum (1)
+    0x29, 0x04, //   Usage Maximum (4)
+    0x15, 0x00, //   Logical Minimum (0)
+    0x25, 0x01, //   Logical Maximum (1)
+    0x75, 0x01, //   Report Size (1)
+    0x95, 0x04, //   Report Count (4)
+    0x81, 0x02, //   Input (Data,Var,Abs)
+    0x95, 0x01, //   Report Count (1)
+    0x75, 0x04, //   Report Size (4)
+    0x81, 0x01, //   Input (Cnst,Arr,Abs)
+    0xc0, // End Collection
+];
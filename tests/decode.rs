@@ -0,0 +1,457 @@
+// SPDX-License-Identifier: MIT
+
+mod common;
+
+use common::KEYBOARD;
+use hid_json::{decode, DecodeOptions, JsonCollection, JsonItemName, JsonUnitSystem};
+
+#[test]
+fn vendor_defined_collection() {
+    for value in [0x80, 0x82, 0xff] {
+        let bytes = [
+            0xa1, value, // Collection (Vendor Defined)
+            0xc0,  // End Collection
+        ];
+        let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+        let collection = decode.items[0].collection.as_ref().unwrap();
+        assert!(matches!(collection, JsonCollection::VendorDefined(v) if *v == value));
+        assert_eq!(
+            serde_json::to_string(collection).unwrap(),
+            format!(r#"{{"VendorDefined":{value}}}"#)
+        );
+    }
+}
+
+#[test]
+fn reserved_collection() {
+    let bytes = [
+        0xa1, 0x07, // Collection (Reserved)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    assert!(matches!(
+        decode.items[0].collection,
+        Some(JsonCollection::Reserved)
+    ));
+}
+
+#[test]
+fn signed_globals() {
+    let bytes = [
+        0x15, 0x81, // Logical Minimum (-127)
+        0x16, 0x00, 0x80, // Logical Minimum (-32768)
+        0x17, 0x00, 0x00, 0x00, 0x80, // Logical Minimum (-2147483648)
+        0x35, 0xff, // Physical Minimum (-1)
+        0x36, 0x00, 0x80, // Physical Minimum (-32768)
+        0x37, 0x00, 0x00, 0x00, 0x80, // Physical Minimum (-2147483648)
+        0x25, 0x7f, // Logical Maximum (127)
+        0x26, 0xff, 0x00, // Logical Maximum (255)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    let values: Vec<Option<i32>> = decode.items.iter().map(|item| item.value).collect();
+    assert_eq!(
+        values,
+        [
+            Some(-127),
+            Some(-32768),
+            Some(i32::MIN),
+            Some(-1),
+            Some(-32768),
+            Some(i32::MIN),
+            Some(127),
+            Some(255),
+        ]
+    );
+}
+
+#[test]
+fn unsigned_globals() {
+    let bytes = [
+        0x75, 0x81, // Report Size (129)
+        0x96, 0x00, 0x80, // Report Count (32768)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    assert_eq!(decode.items[0].value, Some(129));
+    assert_eq!(decode.items[1].value, Some(32768));
+}
+
+#[test]
+fn keyboard_flags() {
+    let decode = decode(KEYBOARD, DecodeOptions::default()).unwrap();
+    let flags: Vec<(usize, (bool, bool, bool))> = decode
+        .items
+        .iter()
+        .filter_map(|item| {
+            let flags = item.flags.as_ref()?;
+            Some((
+                item.offset,
+                (flags.constant, flags.variable, flags.relative),
+            ))
+        })
+        .collect();
+    assert_eq!(
+        flags,
+        [
+            // The modifier keys: Data, Variable, Absolute
+            (20, (false, true, false)),
+            (26, (true, false, false)),
+            (38, (false, true, false)),
+            (44, (true, false, false)),
+            // The keycode array: Data, Array, Absolute
+            (60, (false, false, false)),
+        ]
+    );
+
+    let modifiers = &decode.items[10];
+    assert!(matches!(modifiers.item_name, JsonItemName::Input));
+    assert_eq!(modifiers.value, Some(0x02));
+    let flags = modifiers.flags.as_ref().unwrap();
+    assert!(!flags.wrap && !flags.nonlinear && !flags.no_preferred_state);
+    assert!(!flags.null_state && !flags.buffered_bytes);
+    // Input items don't have a volatile bit
+    assert_eq!(flags.volatile, None);
+
+    let leds = &decode.items[19];
+    assert!(matches!(leds.item_name, JsonItemName::Output));
+    assert_eq!(leds.flags.as_ref().unwrap().volatile, Some(false));
+}
+
+#[test]
+fn units() {
+    let bytes = [
+        0x65, 0x11, // Unit (SI Linear: cm)
+        0x65, 0x14, // Unit (English Rotation: deg)
+        0x66, 0x11, 0xf0, // Unit (SI Linear: cm/s)
+        0x65, 0x00, // Unit (None)
+        0x65, 0x15, // Unit (Reserved)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    let units: Vec<_> = decode
+        .items
+        .iter()
+        .map(|item| item.unit.as_ref().unwrap())
+        .collect();
+
+    assert!(matches!(units[0].system, JsonUnitSystem::SILinear));
+    assert_eq!(units[0].length, Some(1));
+    assert_eq!(units[0].time, None);
+    assert_eq!(decode.items[0].value, Some(0x11));
+
+    assert!(matches!(units[1].system, JsonUnitSystem::EnglishRotation));
+    assert_eq!(units[1].length, Some(1));
+    assert_eq!(units[1].mass, None);
+
+    assert!(matches!(units[2].system, JsonUnitSystem::SILinear));
+    assert_eq!(units[2].length, Some(1));
+    assert_eq!(units[2].time, Some(-1));
+
+    assert!(matches!(units[3].system, JsonUnitSystem::None));
+    assert_eq!(units[3].length, None);
+
+    assert!(matches!(units[4].system, JsonUnitSystem::Reserved(5)));
+    assert_eq!(units[4].length, Some(1));
+
+    let text = hid_json::text::to_text(&decode.items);
+    let lines: Vec<&str> = text.lines().collect();
+    assert!(lines[0].contains("// Unit (cm)"));
+    assert!(lines[1].contains("// Unit (deg)"));
+    assert!(lines[2].contains("// Unit (cm/s)"));
+}
+
+#[test]
+fn digitizer_resolution() {
+    let bytes = [
+        0x05, 0x0d, // Usage Page (Digitizers)
+        0x09, 0x02, // Usage (Pen)
+        0xa1, 0x01, // Collection (Application)
+        0x05, 0x01, //   Usage Page (Generic Desktop)
+        0x09, 0x30, //   Usage (X)
+        0x65, 0x11, //   Unit (SI Linear: cm)
+        0x55, 0x0e, //   Unit Exponent (-2)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x26, 0xff, 0x7f, //   Logical Maximum (32767)
+        0x35, 0x00, //   Physical Minimum (0)
+        0x46, 0xe8, 0x03, //   Physical Maximum (1000)
+        0x75, 0x10, //   Report Size (16)
+        0x95, 0x01, //   Report Count (1)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0x09, 0x31, //   Usage (Y)
+        0x65, 0x00, //   Unit (None)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    let x = &decode.items[13];
+    assert!(matches!(x.item_name, JsonItemName::Input));
+    // 32767 counts over 1000 * 10^-2 cm
+    let resolution = x.resolution.unwrap();
+    assert!((resolution - 3276.7).abs() < 1e-9, "{resolution}");
+    assert_eq!(x.resolution_unit.as_deref(), Some("cm"));
+
+    // No resolution without a unit
+    let y = &decode.items[16];
+    assert!(matches!(y.item_name, JsonItemName::Input));
+    assert_eq!(y.resolution, None);
+    assert_eq!(y.resolution_unit, None);
+}
+
+#[test]
+fn long_item_offsets() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0xfe, 0x03, 0xf0, 0xaa, 0xbb, 0xcc, // Long Item (tag 0xf0, 3 bytes)
+        0x09, 0x02, // Usage (Mouse)
+        0xfe, 0x00, 0xf1, // Long Item (tag 0xf1, 0 bytes)
+        0xa1, 0x01, // Collection (Application)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    let offsets: Vec<usize> = decode.items.iter().map(|item| item.offset).collect();
+    assert_eq!(offsets, [0, 2, 8, 10, 13, 15]);
+    for item in &decode.items {
+        let data = item.data.as_deref().unwrap();
+        assert_eq!(data, &bytes[item.offset..item.offset + data.len()]);
+    }
+
+    let long_item = decode.items[1].long_item.as_ref().unwrap();
+    assert!(matches!(decode.items[1].item_name, JsonItemName::LongItem));
+    assert_eq!(long_item.tag, 0xf0);
+    assert_eq!(long_item.data, [0xaa, 0xbb, 0xcc]);
+    let long_item = decode.items[3].long_item.as_ref().unwrap();
+    assert_eq!(long_item.tag, 0xf1);
+    assert!(long_item.data.is_empty());
+
+    assert!(matches!(decode.items[2].item_name, JsonItemName::Usage));
+    assert_eq!(decode.items[2].usage.as_deref(), Some("Mouse"));
+    assert!(matches!(
+        decode.items[4].item_name,
+        JsonItemName::Collection
+    ));
+    assert!(matches!(
+        decode.items[5].item_name,
+        JsonItemName::EndCollection
+    ));
+}
+
+#[test]
+fn extended_usage() {
+    let bytes = [
+        0x05, 0x0c, // Usage Page (Consumer)
+        0x0b, 0x24, 0x02, 0x0c, 0x00, // Usage (Consumer: AC Back)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    assert_eq!(decode.items[1].usage.as_deref(), Some("AC Back"));
+    assert_eq!(decode.items[1].usage_page.as_deref(), Some("Consumer"));
+}
+
+#[test]
+fn extended_usage_other_usage_page() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x0b, 0x24, 0x02, 0x0c, 0x00, // Usage (Consumer: AC Back)
+        0x1b, 0x01, 0x00, 0x09, 0x00, // Usage Minimum (Button: Button 1)
+        0x09, 0x30, // Usage (X)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default()).unwrap();
+    assert_eq!(decode.items[1].usage.as_deref(), Some("AC Back"));
+    assert_eq!(decode.items[1].usage_page.as_deref(), Some("Consumer"));
+    assert_eq!(decode.items[2].usage_minimum.as_deref(), Some("Button 1"));
+    assert_eq!(decode.items[2].usage_page.as_deref(), Some("Button"));
+    // The extended usages don't change the Usage Page in effect
+    assert_eq!(decode.items[3].usage.as_deref(), Some("X"));
+    assert_eq!(
+        decode.items[3].usage_page.as_deref(),
+        Some("Generic Desktop")
+    );
+}
+
+#[test]
+fn truncated_item_error() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x02, // Usage (Mouse)
+        0xa1, 0x01, // Collection (Application)
+        0x26, 0xff, // Logical Maximum, missing one byte
+    ];
+    let Err(e) = decode(&bytes, DecodeOptions::default()) else {
+        panic!("Truncated item must fail to decode");
+    };
+    let message = format!("{e:#}");
+    assert!(
+        message.contains("the item at offset 6 exceeds the descriptor length of 8 bytes"),
+        "{message}"
+    );
+    assert!(message.contains("05 01 09 02 a1 01 [26 ff]"), "{message}");
+}
+
+#[test]
+fn truncated_item_after_long_item_error() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0xfe, 0x01, 0xf0, 0xaa, // Long Item (tag 0xf0, 1 byte)
+        0x09, 0x02, // Usage (Mouse)
+        0x26, 0xff, // Logical Maximum, missing one byte
+    ];
+    let Err(e) = decode(&bytes, DecodeOptions::default()) else {
+        panic!("Truncated item must fail to decode");
+    };
+    let message = format!("{e:#}");
+    assert!(message.contains("the item at offset 8"), "{message}");
+    assert!(message.contains("fe 01 f0 aa 09 02 [26 ff]"), "{message}");
+}
+
+#[test]
+fn truncated_long_item_error() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0xfe, 0x10, 0xf0, 0xaa, 0xbb, // Long Item (tag 0xf0), missing 14 bytes
+    ];
+    let Err(e) = decode(&bytes, DecodeOptions::default()) else {
+        panic!("Truncated long item must fail to decode");
+    };
+    let message = format!("{e:#}");
+    assert!(
+        message.contains("the item at offset 2 exceeds the descriptor length of 7 bytes"),
+        "{message}"
+    );
+    assert!(message.contains("05 01 [fe 10 f0 aa bb]"), "{message}");
+}
+
+/// Two top-level collections with Report ID 1 and 2 after a preamble with
+/// an empty collection. The comments are the offsets of the items.
+const TWO_REPORTS: &[u8] = &[
+    0x05, 0x01, //  0: Usage Page (Generic Desktop)
+    0x15, 0x00, //  2: Logical Minimum (0)
+    0xa1, 0x02, //  4: Collection (Logical)
+    0xc0, //        6: End Collection
+    0x09, 0x02, //  7: Usage (Mouse)
+    0xa1, 0x01, //  9: Collection (Application)
+    0x85, 0x01, // 11:   Report ID (1)
+    0x75, 0x08, // 13:   Report Size (8)
+    0x95, 0x01, // 15:   Report Count (1)
+    0x09, 0x30, // 17:   Usage (X)
+    0x81, 0x02, // 19:   Input (Data,Var,Abs)
+    0xc0, //       21: End Collection
+    0x05, 0x0c, // 22: Usage Page (Consumer)
+    0x09, 0x01, // 24: Usage (Consumer Control)
+    0xa1, 0x01, // 26: Collection (Application)
+    0x85, 0x02, // 28:   Report ID (2)
+    0xa1, 0x02, // 30:   Collection (Logical)
+    0x09, 0xe9, // 32:     Usage (Volume Increment)
+    0x81, 0x02, // 34:     Input (Data,Var,Abs)
+    0xc0, //       36:   End Collection
+    0xc0, //       37: End Collection
+];
+
+fn filtered_offsets(report_id: u32) -> Vec<usize> {
+    let options = DecodeOptions::default().report_id(Some(report_id));
+    let decode = decode(TWO_REPORTS, options).unwrap();
+    decode.items.iter().map(|item| item.offset).collect()
+}
+
+#[test]
+fn report_id_filter_preamble() {
+    let offsets = filtered_offsets(2);
+    assert!(offsets.starts_with(&[0, 2, 7]), "{offsets:?}");
+    // The preamble's collection holds no item of Report ID 2
+    assert!(!offsets.contains(&4), "{offsets:?}");
+    assert!(!offsets.contains(&6), "{offsets:?}");
+}
+
+#[test]
+fn report_id_filter_other_reports() {
+    let offsets = filtered_offsets(2);
+    for offset in [9, 11, 13, 15, 17, 19, 21] {
+        assert!(!offsets.contains(&offset), "{offset} in {offsets:?}");
+    }
+    let offsets = filtered_offsets(1);
+    for offset in [26, 28, 30, 32, 34, 36, 37] {
+        assert!(!offsets.contains(&offset), "{offset} in {offsets:?}");
+    }
+}
+
+#[test]
+fn report_id_filter_context() {
+    // The Usage Page and Usage of the kept collection are kept even though
+    // Report ID 1 is in effect for them, and so is the nested collection
+    assert_eq!(
+        filtered_offsets(2),
+        [0, 2, 7, 22, 24, 26, 28, 30, 32, 34, 36, 37]
+    );
+}
+
+#[test]
+fn report_id_filter_undeclared_error() {
+    let options = DecodeOptions::default().report_id(Some(3));
+    let Err(e) = decode(TWO_REPORTS, options) else {
+        panic!("Undeclared Report ID must fail to decode");
+    };
+    assert_eq!(
+        e.to_string(),
+        "Report ID 3 is not declared in the report descriptor"
+    );
+}
+
+#[test]
+fn collapsed_items_push_pop() {
+    let bytes = [
+        0x05, 0x01, //  0: Usage Page (Generic Desktop)
+        0x05, 0x01, //  2: Usage Page (Generic Desktop), redundant
+        0xa4, //        4: Push
+        0x05, 0x01, //  5: Usage Page (Generic Desktop), first since the Push
+        0x05, 0x01, //  7: Usage Page (Generic Desktop), redundant
+        0xb4, //        9: Pop
+        0x05, 0x01, // 10: Usage Page (Generic Desktop), first since the Pop
+    ];
+    let collapsed = decode(&bytes, DecodeOptions::default().collapse_redundant(true)).unwrap();
+    let offsets: Vec<usize> = collapsed.items.iter().map(|item| item.offset).collect();
+    assert_eq!(offsets, [0, 4, 5, 9, 10]);
+    assert_eq!(collapsed.collapsed_items, Some(2));
+
+    let plain = decode(&bytes, DecodeOptions::default()).unwrap();
+    assert_eq!(plain.items.len(), 7);
+    assert_eq!(plain.collapsed_items, None);
+}
+
+#[test]
+fn summary_applications() {
+    let bytes = [
+        0xa2, 0x01, 0x00, // Collection (Application), 2 bytes
+        0xc0, //             End Collection
+        0xa0, //             Collection (Physical), 0 bytes
+        0xc0, //             End Collection
+        0xa1, 0x02, //       Collection (Logical)
+        0xc0, //             End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().summary(true)).unwrap();
+    let applications = &decode.summary.as_ref().unwrap().applications;
+    let offsets: Vec<usize> = applications.iter().map(|a| a.offset).collect();
+    assert_eq!(offsets, [0]);
+}
+
+#[test]
+fn debug_details() {
+    let bytes = [
+        0x15, 0x81, // Logical Minimum (-127)
+        0x26, 0xff, 0x00, // Logical Maximum (255)
+        0xfe, 0x01, 0xf0, 0xaa, // Long Item (tag 0xf0, 1 byte)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().debug(true)).unwrap();
+    let debug: Vec<_> = decode
+        .items
+        .iter()
+        .map(|item| item.debug.as_ref().unwrap())
+        .collect();
+
+    assert_eq!(debug[0].prefix, 0x15);
+    assert_eq!(debug[0].size_code, 1);
+    assert_eq!(debug[0].data, [0x81]);
+    assert_eq!(debug[0].value, Some(-127));
+
+    assert_eq!(debug[1].size_code, 2);
+    assert_eq!(debug[1].value, Some(255));
+
+    assert_eq!(debug[2].prefix, 0xfe);
+    assert_eq!(debug[2].data, [0x01, 0xf0, 0xaa]);
+    assert_eq!(debug[2].value, None);
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: MIT
+
+mod common;
+
+use common::{JOYSTICK, KEYBOARD, MOUSE};
+use hid_json::encode::{encode, from_json, EncodeItem};
+use hid_json::{decode, DecodeOptions, JsonDecode, JsonItemName};
+
+fn encode_items(decode: &JsonDecode) -> Vec<EncodeItem> {
+    decode
+        .items
+        .iter()
+        .map(|item| EncodeItem {
+            offset: Some(item.offset),
+            item_name: item.item_name,
+            value: item.value.map(i64::from),
+            children: Vec::new(),
+        })
+        .collect()
+}
+
+fn encode_item(item_name: JsonItemName, value: Option<i64>) -> EncodeItem {
+    EncodeItem {
+        offset: Some(2),
+        item_name,
+        value,
+        children: Vec::new(),
+    }
+}
+
+#[test]
+fn round_trip() {
+    for bytes in [MOUSE, KEYBOARD, JOYSTICK] {
+        let decode = decode(bytes, DecodeOptions::default()).unwrap();
+        for (item, encoded) in decode.items.iter().zip(encode_items(&decode)) {
+            let data = encode(&[encoded]).unwrap();
+            assert_eq!(Some(data), item.data, "item at offset {}", item.offset);
+        }
+        assert_eq!(encode(&encode_items(&decode)).unwrap(), bytes);
+    }
+}
+
+#[test]
+fn round_trip_json() {
+    for bytes in [MOUSE, KEYBOARD, JOYSTICK] {
+        let decode = decode(bytes, DecodeOptions::default()).unwrap();
+        let json = serde_json::to_vec(&decode).unwrap();
+        assert_eq!(from_json(&json).unwrap(), bytes);
+        let json = serde_json::to_vec(&decode.items).unwrap();
+        assert_eq!(from_json(&json).unwrap(), bytes);
+        let json = serde_json::to_vec(&decode.tree().unwrap()).unwrap();
+        assert_eq!(from_json(&json).unwrap(), bytes);
+    }
+}
+
+#[test]
+fn long_item_error() {
+    let err = encode(&[encode_item(JsonItemName::LongItem, None)]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Failed to encode LongItem item at offset 2"
+    );
+    assert_eq!(err.root_cause().to_string(), "Long items are not supported");
+}
+
+#[test]
+fn reserved_item_error() {
+    let err = encode(&[encode_item(JsonItemName::Reserved, Some(1))]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Failed to encode Reserved item at offset 2"
+    );
+    assert_eq!(err.root_cause().to_string(), "Item has no known tag");
+}
+
+#[test]
+fn out_of_range_value_error() {
+    for (item_name, value) in [
+        (JsonItemName::LogicalMaximum, i64::from(i32::MAX) + 1),
+        (JsonItemName::ReportCount, i64::from(u32::MAX) + 1),
+        (JsonItemName::LogicalMinimum, i64::from(i32::MIN) - 1),
+    ] {
+        let err = encode(&[encode_item(item_name, Some(value))]).unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            format!("Value {value} does not fit into 4 bytes")
+        );
+    }
+}
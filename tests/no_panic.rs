@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: MIT
+
+//! A quick check that decoding arbitrary bytes never panics, see the
+//! `fuzz` directory for a more thorough check.
+
+mod common;
+
+use common::{JOYSTICK, KEYBOARD, MOUSE};
+use hid_json::{decode, text, DecodeOptions};
+
+/// A xorshift PRNG, good enough to generate test input
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn decode_all(bytes: &[u8]) {
+    let options = DecodeOptions::default()
+        .debug(true)
+        .collapse_redundant(true)
+        .with_state_diff(true)
+        .with_both_signs(true)
+        .warn_unaligned_fields(true)
+        .summary(true)
+        .with_reports(true);
+    let report_id = bytes.first().map(|&id| u32::from(id));
+    for options in [options.clone(), options.report_id(report_id)] {
+        if let Ok(decode) = decode(bytes, options) {
+            let _ = decode.report_map();
+            let _ = decode.tree();
+            let _ = text::to_text(&decode.items);
+        }
+    }
+}
+
+#[test]
+fn random_bytes() {
+    let mut rng = Rng(0x2545f4914f6cdd1d);
+    for _ in 0..2000 {
+        let length = rng.below(64);
+        let bytes: Vec<u8> = (0..length).map(|_| rng.next() as u8).collect();
+        decode_all(&bytes);
+    }
+}
+
+#[test]
+fn mutated_descriptors() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    for descriptor in [MOUSE, KEYBOARD, JOYSTICK] {
+        for length in 0..=descriptor.len() {
+            decode_all(&descriptor[..length]);
+        }
+        for _ in 0..1000 {
+            let mut bytes = descriptor.to_vec();
+            for _ in 0..=rng.below(4) {
+                let idx = rng.below(bytes.len());
+                bytes[idx] = match rng.below(4) {
+                    0 => 0x00,
+                    1 => 0xff,
+                    _ => rng.next() as u8,
+                };
+            }
+            decode_all(&bytes);
+        }
+    }
+}
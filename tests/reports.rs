@@ -0,0 +1,424 @@
+// SPDX-License-Identifier: MIT
+
+mod common;
+
+use common::KEYBOARD;
+use hid_json::{decode, DecodeOptions, JsonDecode, JsonFieldRole, JsonItemName, JsonReportType};
+
+fn assert_usage_pages_resolve(decode: &JsonDecode) {
+    for item in &decode.items {
+        if item.item_name == JsonItemName::UsagePage {
+            assert!(item.usage_page.is_some(), "item at offset {}", item.offset);
+        }
+    }
+}
+
+#[test]
+fn oversized_field() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x30, // Usage (X)
+        0x77, 0x00, 0x00, 0x01, 0x00, // Report Size (65536)
+        0x97, 0x00, 0x00, 0x01, 0x00, // Report Count (65536)
+        0x81, 0x03, // Input (Cnst,Var,Abs)
+        0x81, 0x03, // Input (Cnst,Var,Abs)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    let warnings: Vec<usize> = decode
+        .warnings
+        .iter()
+        .filter(|w| w.message.contains("maximum report length"))
+        .map(|w| w.offset)
+        .collect();
+    assert_eq!(warnings, [14, 16]);
+
+    let reports = decode.reports.as_ref().unwrap();
+    assert_eq!(reports[0].length, u32::MAX.div_ceil(8));
+    assert_eq!(reports[0].fields[0].bits, u32::MAX);
+    assert_eq!(reports[0].fields[1].bit_offset, u32::MAX);
+
+    let report_map = decode.report_map();
+    assert_eq!(report_map[0].fields[0].bits, u32::MAX);
+}
+
+#[test]
+fn oversized_report_count() {
+    let bytes = [
+        0x09, 0x30, // Usage (X)
+        0x97, 0xff, 0xff, 0xff, 0xff, // Report Count (4294967295)
+        0x75, 0x08, // Report Size (8)
+        0x81, 0x02, // Input (Data,Var,Abs)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    assert!(decode
+        .warnings
+        .iter()
+        .any(|w| w.offset == 9 && w.message.contains("exceeds the maximum of 12288 elements")));
+
+    let reports = decode.reports.as_ref().unwrap();
+    assert_eq!(reports[0].fields[0].report_count, u32::MAX);
+    assert_eq!(reports[0].fields[0].usages.len(), 12288);
+
+    let report_map = decode.report_map();
+    assert_eq!(report_map[0].fields.len(), 12289);
+}
+
+#[test]
+fn capped_field_elements() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x30, // Usage (X)
+        0x75, 0x08, // Report Size (8)
+        0x96, 0x20, 0x4e, // Report Count (20000)
+        0x81, 0x02, // Input (Data,Var,Abs)
+        0x09, 0x31, // Usage (Y)
+        0x95, 0x01, // Report Count (1)
+        0x81, 0x02, // Input (Data,Var,Abs)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_report_map(true)).unwrap();
+    let report = &decode.report_map()[0];
+    assert_eq!(report.length, 20001);
+    assert_eq!(report.fields.len(), 12290);
+    assert_eq!(report.fields[12288].bits, (20000 - 12288) * 8);
+    assert_eq!(report.fields[12288].usage, None);
+    let bits: u32 = report.fields.iter().map(|f| f.bits).sum();
+    assert_eq!(bits, report.length * 8);
+    assert_eq!(report.fields[12289].usage, Some("Y".to_string()));
+}
+
+#[test]
+fn keycode_array() {
+    let decode = decode(KEYBOARD, DecodeOptions::default().with_reports(true)).unwrap();
+    let reports = decode.reports.as_ref().unwrap();
+    let modifiers = &reports[0].fields[0];
+    assert!(modifiers.variable);
+    assert_eq!(modifiers.usages.len(), 8);
+    assert_eq!(modifiers.usages[0], "Keyboard LeftControl");
+    assert_eq!(modifiers.usage_minimum, None);
+
+    let keycodes = &reports[0].fields[2];
+    assert_eq!(keycodes.offset, 60);
+    assert!(!keycodes.variable);
+    assert_eq!(keycodes.usages.len(), 0x66);
+    assert_eq!(keycodes.usages[4], "Keyboard A");
+    assert!(keycodes.usage_minimum.is_some());
+    assert!(keycodes.usage_maximum.is_some());
+    assert_eq!(keycodes.role, Some(JsonFieldRole::KeycodeArray));
+    assert_eq!(modifiers.role, None);
+
+    let report_map = decode.report_map();
+    assert!(report_map[0].fields[9..].iter().all(|f| f.usage.is_none()));
+}
+
+#[test]
+fn consumer_array() {
+    let bytes = [
+        0x05, 0x0c, // Usage Page (Consumer)
+        0x09, 0x01, // Usage (Consumer Control)
+        0xa1, 0x01, // Collection (Application)
+        0x09, 0xcd, //   Usage (Play/Pause)
+        0x09, 0xe9, //   Usage (Volume Increment)
+        0x09, 0xea, //   Usage (Volume Decrement)
+        0x1a, 0x24, 0x02, //   Usage Minimum (AC Back)
+        0x2a, 0x25, 0x02, //   Usage Maximum (AC Forward)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x25, 0x04, //   Logical Maximum (4)
+        0x75, 0x08, //   Report Size (8)
+        0x95, 0x02, //   Report Count (2)
+        0x81, 0x00, //   Input (Data,Arr,Abs)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    let field = &decode.reports.as_ref().unwrap()[0].fields[0];
+    assert!(!field.variable);
+    assert_eq!(
+        field.usages,
+        [
+            "Play/Pause",
+            "Volume Increment",
+            "Volume Decrement",
+            "AC Back",
+            "AC Forward"
+        ]
+    );
+    assert_eq!(field.usage_minimum.as_deref(), Some("AC Back"));
+    assert_eq!(field.usage_maximum.as_deref(), Some("AC Forward"));
+    assert_eq!(field.role, Some(JsonFieldRole::ConsumerControlArray));
+}
+
+#[test]
+fn oversized_usage_range() {
+    let bytes = [
+        0x05, 0x0c, // Usage Page (Consumer)
+        0x19, 0x00, // Usage Minimum (0)
+        0x2a, 0xff, 0xff, // Usage Maximum (65535)
+        0x26, 0xff, 0xff, // Logical Maximum (65535)
+        0x75, 0x10, // Report Size (16)
+        0x95, 0x01, // Report Count (1)
+        0x81, 0x00, // Input (Data,Arr,Abs)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    let field = &decode.reports.as_ref().unwrap()[0].fields[0];
+    assert_eq!(field.usages.len(), 12288);
+}
+
+#[test]
+fn scale_collections() {
+    let bytes = [
+        0x05, 0x8d, // Usage Page (Scales)
+        0x09, 0x01, // Usage (Scales)
+        0xa1, 0x01, // Collection (Application)
+        0x85, 0x01, //   Report ID (1)
+        0x09, 0x22, //   Usage (Scale Data Report)
+        0xa1, 0x02, //   Collection (Logical)
+        0x09, 0x30, //     Usage (Data Weight)
+        0x15, 0x00, //     Logical Minimum (0)
+        0x26, 0xff, 0x7f, //     Logical Maximum (32767)
+        0x75, 0x10, //     Report Size (16)
+        0x95, 0x01, //     Report Count (1)
+        0x81, 0x02, //     Input (Data,Var,Abs)
+        0x09, 0x40, //     Usage (Weight Unit)
+        0x09, 0x70, //     Usage (Scale Status)
+        0x75, 0x08, //     Report Size (8)
+        0x95, 0x02, //     Report Count (2)
+        0x81, 0x02, //     Input (Data,Var,Abs)
+        0xc0, //   End Collection
+        0x05, 0x40, //   Usage Page (Medical Instrument)
+        0x09, 0x01, //   Usage (Medical Ultrasound)
+        0x95, 0x01, //   Report Count (1)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    assert_usage_pages_resolve(&decode);
+
+    // The usage of a collection is the Usage item preceding it
+    let collection_usage = |offset: usize| {
+        decode
+            .items
+            .iter()
+            .find(|item| item.offset == offset)
+            .and_then(|item| item.usage.clone())
+    };
+    let collections: Vec<Option<String>> = decode.reports.as_ref().unwrap()[0]
+        .fields
+        .iter()
+        .map(|field| field.collection.clone())
+        .collect();
+    assert!(collections.iter().all(Option::is_some));
+    assert_eq!(
+        collections,
+        [
+            collection_usage(8),
+            collection_usage(8),
+            collection_usage(2)
+        ]
+    );
+}
+
+#[test]
+fn sensor_scaling() {
+    let bytes = [
+        0x05, 0x20, // Usage Page (Sensors)
+        0x0a, 0x83, 0x04, // Usage (Data Field: Quaternion)
+        0x16, 0x01, 0x80, // Logical Minimum (-32767)
+        0x26, 0xff, 0x7f, // Logical Maximum (32767)
+        0x55, 0x0c, // Unit Exponent (-4)
+        0x75, 0x10, // Report Size (16)
+        0x95, 0x04, // Report Count (4)
+        0x81, 0x02, // Input (Data,Var,Abs)
+        0x0a, 0x53, 0x04, // Usage (Data Field: Acceleration Axis X)
+        0x66, 0x11, 0xe0, // Unit (SI Linear: cm/s^2)
+        0x55, 0x0e, // Unit Exponent (-2)
+        0x95, 0x01, // Report Count (1)
+        0x81, 0x02, // Input (Data,Var,Abs)
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    let fields = &decode.reports.as_ref().unwrap()[0].fields;
+
+    let quaternion = &fields[0];
+    assert_eq!(quaternion.role, Some(JsonFieldRole::OrientationData));
+    assert_eq!(quaternion.unit_exponent, Some(-4));
+    assert!((quaternion.resolution.unwrap() - 10000.0).abs() < 1e-6);
+    assert_eq!(quaternion.resolution_unit, None);
+
+    let acceleration = &fields[1];
+    assert_eq!(acceleration.role, Some(JsonFieldRole::MotionData));
+    assert_eq!(acceleration.unit_exponent, Some(-2));
+    assert!((acceleration.resolution.unwrap() - 100.0).abs() < 1e-9);
+    assert_eq!(acceleration.resolution_unit.as_deref(), Some("cm/s^2"));
+}
+
+#[test]
+fn fido_reports() {
+    let bytes = [
+        0x06, 0xd0, 0xf1, // Usage Page (FIDO Alliance)
+        0x09, 0x01, // Usage (U2F Authenticator Device)
+        0xa1, 0x01, // Collection (Application)
+        0x09, 0x20, //   Usage (Input Report Data)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x26, 0xff, 0x00, //   Logical Maximum (255)
+        0x75, 0x08, //   Report Size (8)
+        0x95, 0x40, //   Report Count (64)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0x09, 0x21, //   Usage (Output Report Data)
+        0x91, 0x02, //   Output (Data,Var,Abs)
+        0x09, 0x21, //   Usage (Output Report Data)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    assert!(decode.items[0].usage_page.is_some());
+
+    let reports = decode.reports.as_ref().unwrap();
+    let roles: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            (
+                report.report_type,
+                report.length,
+                report.fields.iter().map(|f| f.role).collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        roles,
+        [
+            (
+                JsonReportType::Input,
+                128,
+                vec![Some(JsonFieldRole::FidoReportData), None]
+            ),
+            (
+                JsonReportType::Output,
+                64,
+                vec![Some(JsonFieldRole::FidoReportData)]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn monitor_controls() {
+    let bytes = [
+        0x05, 0x80, // Usage Page (Monitor)
+        0x09, 0x01, // Usage (Monitor Control)
+        0xa1, 0x01, // Collection (Application)
+        0x85, 0x01, //   Report ID (1)
+        0x05, 0x82, //   Usage Page (VESA Virtual Controls)
+        0x09, 0x10, //   Usage (Brightness)
+        0x09, 0x12, //   Usage (Contrast)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x26, 0xff, 0x00, //   Logical Maximum (255)
+        0x75, 0x08, //   Report Size (8)
+        0x95, 0x02, //   Report Count (2)
+        0xb1, 0x02, //   Feature (Data,Var,Abs)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    assert_usage_pages_resolve(&decode);
+
+    let report = &decode.reports.as_ref().unwrap()[0];
+    assert_eq!(report.report_type, JsonReportType::Feature);
+    let field = &report.fields[0];
+    assert_eq!(field.role, None);
+    assert_eq!(field.collection, decode.items[1].usage);
+    assert_eq!(
+        field.usages,
+        [
+            decode.items[5].usage.clone().unwrap(),
+            decode.items[6].usage.clone().unwrap()
+        ]
+    );
+}
+
+#[test]
+fn arcade_controls() {
+    let bytes = [
+        0x05, 0x91, // Usage Page (Arcade)
+        0x09, 0x02, // Usage (Coin Door)
+        0xa1, 0x01, // Collection (Application)
+        0x09, 0x36, //   Usage (Coin Drawer Start)
+        0x09, 0x37, //   Usage (Coin Drawer Service)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x25, 0x01, //   Logical Maximum (1)
+        0x75, 0x01, //   Report Size (1)
+        0x95, 0x02, //   Report Count (2)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0x75, 0x06, //   Report Size (6)
+        0x95, 0x01, //   Report Count (1)
+        0x81, 0x01, //   Input (Cnst,Arr,Abs)
+        0x05, 0x92, //   Usage Page (Gaming Device)
+        0x09, 0x01, //   Usage (1)
+        0x26, 0xff, 0x00, //   Logical Maximum (255)
+        0x75, 0x08, //   Report Size (8)
+        0x81, 0x02, //   Input (Data,Var,Abs)
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    assert_usage_pages_resolve(&decode);
+
+    let fields = &decode.reports.as_ref().unwrap()[0].fields;
+    assert_eq!(fields.len(), 3);
+    assert!(fields.iter().all(|field| field.collection.is_some()));
+    assert!(fields.iter().all(|field| field.role.is_none()));
+    assert_eq!(fields[0].collection, decode.items[1].usage);
+    assert_eq!(fields[2].collection, decode.items[1].usage);
+}
+
+#[test]
+fn eye_tracker() {
+    let bytes = [
+        0x05, 0x12, // Usage Page (Eye and Head Trackers)
+        0x09, 0x01, // Usage (Eye Tracker)
+        0xa1, 0x01, // Collection (Application)
+        0x09, 0x10, //   Usage (Tracking Data)
+        0xa1, 0x00, //   Collection (Physical)
+        0x09, 0x20, //     Usage (Sensor Timestamp)
+        0x15, 0x00, //     Logical Minimum (0)
+        0x27, 0xff, 0xff, 0xff, 0x7f, //     Logical Maximum (2147483647)
+        0x75, 0x20, //     Report Size (32)
+        0x95, 0x01, //     Report Count (1)
+        0x81, 0x02, //     Input (Data,Var,Abs)
+        0x09, 0x24, //     Usage (Gaze Point)
+        0x17, 0x00, 0x00, 0x00, 0x80, //     Logical Minimum (-2147483648)
+        0x95, 0x03, //     Report Count (3)
+        0x81, 0x02, //     Input (Data,Var,Abs)
+        0xc0, //   End Collection
+        0xc0, // End Collection
+    ];
+    let decode = decode(&bytes, DecodeOptions::default().with_reports(true)).unwrap();
+    assert_usage_pages_resolve(&decode);
+
+    let fields = &decode.reports.as_ref().unwrap()[0].fields;
+    let roles: Vec<_> = fields.iter().map(|field| field.role).collect();
+    assert_eq!(roles, [None, Some(JsonFieldRole::GazeData)]);
+    assert!(fields[0].collection.is_some());
+    assert!(fields
+        .iter()
+        .all(|field| field.collection == decode.items[3].usage));
+}
+
+#[test]
+fn fields_only_when_requested() {
+    let bytes = [
+        0x05, 0x01, // Usage Page (Generic Desktop)
+        0x09, 0x30, // Usage (X)
+        0x75, 0x08, // Report Size (8)
+        0x96, 0x00, 0x30, // Report Count (12288)
+        0x81, 0x02, // Input (Data,Var,Abs)
+    ];
+    let plain = decode(&bytes, DecodeOptions::default()).unwrap();
+    assert!(plain.reports.is_none());
+    assert!(plain.report_map().is_empty());
+
+    let with_report_map = decode(&bytes, DecodeOptions::default().with_report_map(true)).unwrap();
+    assert!(with_report_map.reports.is_none());
+    let report_map = with_report_map.report_map();
+    assert_eq!(report_map[0].length, 12288);
+    assert_eq!(report_map[0].fields.len(), 12288);
+    assert!(report_map[0]
+        .fields
+        .iter()
+        .all(|f| f.usage == report_map[0].fields[0].usage));
+}
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: MIT
+
+#![cfg(feature = "validate-schema")]
+
+mod common;
+
+use common::{JOYSTICK, KEYBOARD, MOUSE};
+use hid_json::{decode, validate_schema, DecodeOptions};
+
+#[test]
+fn output_matches_schema() {
+    let options = DecodeOptions::default()
+        .debug(true)
+        .with_state_diff(true)
+        .with_both_signs(true)
+        .warn_unaligned_fields(true)
+        .summary(true)
+        .with_reports(true);
+    for bytes in [MOUSE, KEYBOARD, JOYSTICK] {
+        for options in [DecodeOptions::default(), options.clone()] {
+            let decode = decode(bytes, options).unwrap();
+            validate_schema(&decode).unwrap();
+        }
+    }
+}
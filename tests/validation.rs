@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: MIT
+
+//! The structural checks whose warnings make `--strict` fail
+
+use hid_json::{decode, DecodeOptions};
+
+fn warnings(bytes: &[u8]) -> Vec<(usize, String)> {
+    let decode = decode(bytes, DecodeOptions::default()).unwrap();
+    decode
+        .warnings
+        .into_iter()
+        .map(|w| (w.offset, w.message))
+        .collect()
+}
+
+#[test]
+fn unbalanced_push_pop() {
+    let bytes = [
+        0xa4, // Push
+        0xa4, // Push
+        0xb4, // Pop
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [(0, "Push without a matching Pop".to_string())]
+    );
+
+    let bytes = [
+        0xa4, // Push
+        0xb4, // Pop
+        0xb4, // Pop
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [(2, "Pop without a matching Push".to_string())]
+    );
+}
+
+#[test]
+fn unbalanced_collection() {
+    let bytes = [
+        0xa1, 0x01, // Collection (Application)
+        0xa1, 0x00, //   Collection (Physical)
+        0xc0, //       End Collection
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [(0, "Collection without a matching EndCollection".to_string())]
+    );
+
+    let bytes = [
+        0xa1, 0x01, // Collection (Application)
+        0xc0, //       End Collection
+        0xc0, //       End Collection
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [(3, "EndCollection without a matching Collection".to_string())]
+    );
+}
+
+#[test]
+fn missing_report_size_and_count() {
+    let bytes = [
+        0x95, 0x01, // Report Count (1)
+        0x81, 0x03, // Input (Cnst,Var,Abs)
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [(2, "Input without a preceding Report Size".to_string())]
+    );
+
+    let bytes = [
+        0x75, 0x08, // Report Size (8)
+        0x81, 0x03, // Input (Cnst,Var,Abs)
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [(2, "Input without a preceding Report Count".to_string())]
+    );
+}
+
+#[test]
+fn zero_report_count() {
+    let bytes = [
+        0x75, 0x08, // Report Size (8)
+        0x95, 0x00, // Report Count (0)
+    ];
+    assert_eq!(warnings(&bytes), [(2, "Report Count is 0".to_string())]);
+}
+
+#[test]
+fn warnings_sorted_by_offset() {
+    // The unmatched Push is only found after the last item
+    let bytes = [
+        0xa4, // Push
+        0xb1, 0x03, // Feature (Cnst,Var,Abs)
+    ];
+    assert_eq!(
+        warnings(&bytes),
+        [
+            (0, "Push without a matching Pop".to_string()),
+            (1, "Feature without a preceding Report Size".to_string()),
+            (1, "Feature without a preceding Report Count".to_string()),
+        ]
+    );
+}